@@ -1,11 +1,35 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::types::{time::OffsetDateTime, Json};
 
-#[derive(Serialize)]
+use crate::api_tokens::TokenScope;
+use crate::category_members::{CategoryAccess, MemberRole};
+
+/// Distinguishes absent field (`None`) from explicit `null` (`Some(None)`)
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize, Debug)]
 pub struct Category {
     pub id: i64,
     pub name: Option<String>,
     pub num_words: i64,
     pub sample_words: Vec<String>,
+    /// Place in user-defined order, pinned categories go first regardless
+    pub position: i64,
+    pub pinned: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub archived_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    /// What current user may do with category, which may be shared with them
+    pub access: CategoryAccess,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -13,28 +37,386 @@ pub struct CategoryCreateRequest {
     pub name: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Absent fields are left unchanged, `null` name clears it
+#[derive(Deserialize, Default)]
 pub struct CategoryUpdateRequest {
-    pub name: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub name: Option<Option<String>>,
+    #[serde(default)]
+    pub pinned: Option<bool>,
+    /// Archives category when `true`, restores it when `false`
+    #[serde(default)]
+    pub archived: Option<bool>,
 }
 
-#[derive(Serialize)]
+/// New order of categories. Categories not listed keep their relative order
+/// after listed ones.
+#[derive(Deserialize)]
+pub struct CategoryReorderRequest {
+    pub category_ids: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CategoryMemberAddRequest {
+    pub username: String,
+    pub role: MemberRole,
+}
+
+/// User category is shared with
+#[derive(Serialize, Debug)]
+pub struct CategoryMember {
+    pub user_id: i64,
+    pub username: String,
+    pub role: MemberRole,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CategoryMembers {
+    pub members: Vec<CategoryMember>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CategoryDeleteReport {
+    pub words_deleted: u64,
+    pub words_moved: u64,
+}
+
+#[derive(Serialize, Debug)]
 pub struct Categories {
     pub categories: Vec<Category>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct WordCreateRequest {
     pub word: String,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub difficulty: Option<u8>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Serialize, Debug)]
+/// Absent fields are left unchanged, `null` clears optional field
+#[derive(Deserialize, Default)]
+pub struct WordUpdateRequest {
+    #[serde(default)]
+    pub word: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub note: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub difficulty: Option<Option<u8>>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Debug, sqlx::FromRow)]
 pub struct Word {
     pub id: i64,
     pub word: String,
+    pub note: Option<String>,
+    /// From 1 to 5
+    pub difficulty: Option<u8>,
+    pub tags: Json<Vec<String>>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Debug)]
 pub struct Words {
     pub words: Vec<Word>,
+    /// Pass as `cursor` to get next page, absent on last page
+    pub next_cursor: Option<String>,
+    /// Number of words matching filter on all pages
+    pub total: i64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum WordsBulkAction {
+    Move { target_category_id: i64 },
+    Copy { target_category_id: i64 },
+    Delete,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WordsBulkRequest {
+    pub word_ids: Vec<i64>,
+    #[serde(flatten)]
+    pub action: WordsBulkAction,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WordsBulkReport {
+    pub affected: u64,
+    /// Words not moved or copied because target category already has them
+    pub skipped_word_ids: Vec<i64>,
+}
+
+/// Counts of entries processed by word import
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct WordImportReport {
+    pub added: usize,
+    pub duplicate: usize,
+    pub invalid: usize,
+}
+
+/// Portable export of all user's categories and words
+#[derive(Serialize, Deserialize)]
+pub struct WordsArchive {
+    pub version: u32,
+    pub categories: Vec<ArchiveCategory>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArchiveCategory {
+    pub name: Option<String>,
+    pub words: Vec<ArchiveWord>,
+}
+
+/// Word with its metadata. Archives of version 1 have bare words instead.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(from = "ArchiveWordEntry")]
+pub struct ArchiveWord {
+    pub word: String,
+    pub note: Option<String>,
+    pub difficulty: Option<u8>,
+    pub tags: Vec<String>,
+}
+
+impl From<String> for ArchiveWord {
+    fn from(word: String) -> Self {
+        ArchiveWord {
+            word,
+            note: None,
+            difficulty: None,
+            tags: vec![],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ArchiveWordEntry {
+    Bare(String),
+    Full {
+        word: String,
+        #[serde(default)]
+        note: Option<String>,
+        #[serde(default)]
+        difficulty: Option<u8>,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+}
+
+impl From<ArchiveWordEntry> for ArchiveWord {
+    fn from(entry: ArchiveWordEntry) -> Self {
+        match entry {
+            ArchiveWordEntry::Bare(word) => word.into(),
+            ArchiveWordEntry::Full {
+                word,
+                note,
+                difficulty,
+                tags,
+            } => ArchiveWord {
+                word,
+                note,
+                difficulty,
+                tags,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ArchiveImportReport {
+    /// Number of created categories
+    pub categories: usize,
+    #[serde(flatten)]
+    pub words: WordImportReport,
+}
+
+#[derive(Deserialize)]
+pub struct GameCreateRequest {
+    /// Name of user who composes text from supplied words
+    pub composer: String,
+    /// Categories of current user to draw game's words from
+    pub category_ids: Vec<i64>,
+    pub words_count: Option<u32>,
+    /// Don't draw words used in this many latest games supplied by current user
+    pub exclude_recent_games: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Game {
+    pub id: i64,
+    pub user_words_id: i64,
+    pub user_words_name: String,
+    pub user_composed_id: i64,
+    pub user_composed_name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub finished_at: Option<OffsetDateTime>,
+    pub words: Vec<Word>,
+}
+
+#[derive(Serialize)]
+pub struct Games {
+    pub games: Vec<Game>,
+}
+
+#[derive(Deserialize)]
+pub struct CompositionSubmitRequest {
+    pub text: String,
+    /// Ids of game's words which were used in text
+    pub used_word_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Composition {
+    pub game_id: i64,
+    pub text: String,
+    pub used_words: Vec<Word>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SessionInfo {
+    pub id: i64,
+    pub created_user_agent: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_used_at: OffsetDateTime,
+    /// Whether this is the session request was made with
+    pub current: bool,
+}
+
+#[derive(Serialize)]
+pub struct Sessions {
+    pub sessions: Vec<SessionInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct ApiTokenCreateRequest {
+    pub name: String,
+    pub scope: TokenScope,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub scope: TokenScope,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct ApiTokens {
+    pub tokens: Vec<ApiToken>,
+}
+
+/// Newly created token, the only time its secret is shown
+#[derive(Serialize, Debug)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+/// Copy of word in one of user's categories
+#[derive(Serialize, Debug)]
+pub struct DuplicateOccurrence {
+    pub word_id: i64,
+    pub category_id: i64,
+    pub category_name: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DuplicateWord {
+    pub word: String,
+    pub occurrences: Vec<DuplicateOccurrence>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Duplicates {
+    pub duplicates: Vec<DuplicateWord>,
+}
+
+#[derive(Deserialize)]
+pub struct DuplicateMergeRequest {
+    /// Copy which stays, other copies of the same word are removed
+    pub keep_word_id: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DuplicateMergeReport {
+    pub kept_word_id: i64,
+    pub removed: u64,
+}
+
+/// Found words of single category
+#[derive(Serialize, Debug)]
+pub struct SearchCategory {
+    pub category_id: i64,
+    pub category_name: Option<String>,
+    pub words: Vec<Word>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SearchResults {
+    /// Ordered by best matching word, as are words within category
+    pub categories: Vec<SearchCategory>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ShareLinkCreateRequest {
+    /// Link works forever when absent
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+/// Public read-only link to category
+#[derive(Serialize, Debug)]
+pub struct ShareLink {
+    pub id: i64,
+    pub category_id: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct ShareLinks {
+    pub links: Vec<ShareLink>,
+}
+
+/// Newly created link, the only time its token is shown
+#[derive(Serialize, Debug)]
+pub struct CreatedShareLink {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    pub token: String,
+    /// Path of public page, relative to site root
+    pub path: String,
+}
+
+/// Category as shown by share link
+#[derive(Serialize, Debug)]
+pub struct SharedCategory {
+    pub name: Option<String>,
+    pub words: Vec<Word>,
 }
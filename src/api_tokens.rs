@@ -0,0 +1,170 @@
+use base64::Engine;
+use clap::ValueEnum;
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sqlx::{query, query_as, types::time::OffsetDateTime, SqlitePool};
+use time::Duration;
+
+use crate::api_data::{ApiToken, CreatedApiToken};
+use crate::users::secret_digest;
+
+/// Makes tokens recognizable, e.g. by secret scanners
+const TOKEN_PREFIX: &str = "pss_";
+/// `last_used_at` is updated not more often than this
+const TOKEN_TOUCH_INTERVAL: Duration = Duration::minutes(1);
+
+/// What API token allows
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ValueEnum, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// Only safe requests, like GET
+    ReadOnly,
+    ReadWrite,
+}
+
+/// User and scope of token request is authenticated with
+pub struct TokenUser {
+    pub user_id: i64,
+    pub scope: TokenScope,
+}
+
+fn new_token_secret() -> String {
+    let mut secret = [0u8; 32];
+    thread_rng().fill_bytes(&mut secret);
+    format!(
+        "{}{}",
+        TOKEN_PREFIX,
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(secret)
+    )
+}
+
+pub async fn create_token(
+    pool: &SqlitePool,
+    user_id: i64,
+    name: &str,
+    scope: TokenScope,
+) -> sqlx::Result<CreatedApiToken> {
+    let created_at = OffsetDateTime::now_utc();
+    let secret = new_token_secret();
+    let token_digest = secret_digest(&secret);
+    let id = query!(
+        "insert into api_tokens (user_id, name, token_digest, scope, created_at) values (?, ?, ?, ?, ?)",
+        user_id,
+        name,
+        token_digest,
+        scope,
+        created_at
+    )
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    Ok(CreatedApiToken {
+        token: ApiToken {
+            id,
+            name: name.to_owned(),
+            scope,
+            created_at,
+            last_used_at: None,
+        },
+        secret,
+    })
+}
+
+pub async fn list_tokens(pool: &SqlitePool, user_id: i64) -> sqlx::Result<Vec<ApiToken>> {
+    query_as!(
+        ApiToken,
+        r#"select id as "id!", name, scope as "scope: TokenScope",
+          created_at as "created_at: OffsetDateTime",
+          last_used_at as "last_used_at: OffsetDateTime"
+        from api_tokens where user_id = ? order by id"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Revokes user's token, returning false if user has no such token
+pub async fn delete_token(pool: &SqlitePool, user_id: i64, token_id: i64) -> sqlx::Result<bool> {
+    let rows_affected = query!(
+        "delete from api_tokens where id = ? and user_id = ?",
+        token_id,
+        user_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected == 1)
+}
+
+/// Looks up token by secret, bumping its `last_used_at`
+pub async fn get_token_user(pool: &SqlitePool, secret: &str) -> sqlx::Result<Option<TokenUser>> {
+    let token_digest = secret_digest(secret);
+    let record = query!(
+        r#"select id as "id!", user_id, scope as "scope: TokenScope",
+          last_used_at as "last_used_at: OffsetDateTime"
+        from api_tokens where token_digest = ?"#,
+        token_digest
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let record = match record {
+        Some(record) => record,
+        None => return Ok(None),
+    };
+
+    let now = OffsetDateTime::now_utc();
+    if record
+        .last_used_at
+        .is_none_or(|t| now - t > TOKEN_TOUCH_INTERVAL)
+    {
+        query!(
+            "update api_tokens set last_used_at = ? where id = ?",
+            now,
+            record.id
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(Some(TokenUser {
+        user_id: record.user_id,
+        scope: record.scope,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{create_token, delete_token, get_token_user, list_tokens, TokenScope};
+    use crate::test_utils::*;
+
+    #[tokio::test]
+    async fn test_token_lifecycle() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let created = create_token(&pool, user1, "script", TokenScope::ReadOnly)
+            .await
+            .unwrap();
+
+        let token_user = get_token_user(&pool, &created.secret)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token_user.user_id, user1);
+        assert_eq!(token_user.scope, TokenScope::ReadOnly);
+
+        let tokens = list_tokens(&pool, user1).await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert!(tokens[0].last_used_at.is_some());
+
+        assert!(!delete_token(&pool, user2, created.token.id).await.unwrap());
+        assert!(delete_token(&pool, user1, created.token.id).await.unwrap());
+        assert!(get_token_user(&pool, &created.secret)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -2,34 +2,57 @@ use axum::RequestPartsExt;
 use axum::{
     async_trait,
     extract::FromRequestParts,
-    http::{request::Parts, StatusCode},
+    http::{header, request::Parts, HeaderMap},
     Extension,
 };
-use axum_extra::extract::{cookie::Cookie, CookieJar};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use serde::Deserialize;
 use sqlx::SqlitePool;
-use tracing::error;
 
+use crate::api_tokens::{get_token_user, TokenScope};
+use crate::controller::error::ApiError;
 use crate::users::get_session_user;
 
 const COOKIE_NAME: &str = "pss_session";
 
+/// Session request is authenticated with
+pub struct CurrentSession {
+    pub id: i64,
+    pub user_id: i64,
+}
+
+/// User request is authenticated as, either by session cookie or by API token
+/// in `Authorization: Bearer` header
 pub struct SessionUser(pub i64);
 
+/// Token from `Authorization: Bearer` header, if present
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
 #[async_trait]
-impl<S> FromRequestParts<S> for SessionUser
+impl<S> FromRequestParts<S> for CurrentSession
 where
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, &'static str);
+    type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let cookies: CookieJar =
-            CookieJar::from_request_parts(parts, state)
-                .await
-                .map_err(|e| {
-                    error!("Cookies error: {}", e);
-                    (StatusCode::INTERNAL_SERVER_ERROR, "Cookies error")
-                })?;
+        // CSRF check is skipped for requests with bearer token, so such
+        // requests must not be authenticated by cookie
+        if bearer_token(&parts.headers).is_some() {
+            return Err(ApiError::Unauthorized(
+                "Session is required, not API token".to_owned(),
+            ));
+        }
+        let cookies = CookieJar::from_request_parts(parts, state).await?;
         if let Some(session_secret) = cookies.get(COOKIE_NAME) {
             let Extension(pool) = parts
                 .extract::<Extension<SqlitePool>>()
@@ -37,28 +60,96 @@ where
                 .expect("Extract database pool");
             let session_secret_s = session_secret.value();
 
-            if let Some(user_id) =
-                get_session_user(&pool, &session_secret_s)
-                    .await
-                    .map_err(|e| {
-                        error!("Session lookup error: {}", e);
-                        (StatusCode::INTERNAL_SERVER_ERROR, "Session lookup error")
-                    })?
-            {
-                Ok(SessionUser(user_id))
+            if let Some(session) = get_session_user(&pool, session_secret_s).await? {
+                Ok(CurrentSession {
+                    id: session.id,
+                    user_id: session.user_id,
+                })
             } else {
-                Err((StatusCode::UNAUTHORIZED, "Invalid session"))
+                Err(ApiError::Unauthorized("Invalid session".to_owned()))
             }
         } else {
-            Err((StatusCode::UNAUTHORIZED, "Not authenticated"))
+            Err(ApiError::Unauthorized("Not authenticated".to_owned()))
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SessionUser
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(token) = bearer_token(&parts.headers).map(str::to_owned) {
+            let Extension(pool) = parts
+                .extract::<Extension<SqlitePool>>()
+                .await
+                .expect("Extract database pool");
+            return match get_token_user(&pool, &token).await? {
+                Some(token_user)
+                    if token_user.scope == TokenScope::ReadOnly && !parts.method.is_safe() =>
+                {
+                    Err(ApiError::Forbidden("Token is read-only".to_owned()))
+                }
+                Some(token_user) => Ok(SessionUser(token_user.user_id)),
+                None => Err(ApiError::Unauthorized("Invalid token".to_owned())),
+            };
         }
+
+        let session = CurrentSession::from_request_parts(parts, state).await?;
+        Ok(SessionUser(session.user_id))
     }
 }
 
-pub fn session_cookie(secret: String) -> CookieJar {
-    let cookie = Cookie::build(COOKIE_NAME, secret)
-        .http_only(true)
-        .path("/api") // TODO: make configurable or relative to login page
-        .finish();
-    CookieJar::new().add(cookie)
+/// Attributes of cookies set by server
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CookieSettings {
+    /// Cookies must be sent to both /api and /auth, the latter is needed for logout
+    pub path: String,
+    pub domain: Option<String>,
+    /// Send cookies only over HTTPS
+    pub secure: bool,
+}
+
+impl Default for CookieSettings {
+    fn default() -> Self {
+        CookieSettings {
+            path: "/".to_owned(),
+            domain: None,
+            secure: false,
+        }
+    }
+}
+
+impl CookieSettings {
+    /// Creates cookie with configured attributes
+    pub fn cookie(&self, name: &'static str, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::new(name, value);
+        cookie.set_path(self.path.clone());
+        cookie.set_same_site(SameSite::Strict);
+        cookie.set_secure(self.secure);
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie
+    }
+}
+
+pub fn session_cookie(secret: String, settings: &CookieSettings) -> Cookie<'static> {
+    let mut cookie = settings.cookie(COOKIE_NAME, secret);
+    cookie.set_http_only(true);
+    cookie
+}
+
+/// Session secret from request cookies, if present
+pub fn session_secret(cookies: &CookieJar) -> Option<&str> {
+    cookies.get(COOKIE_NAME).map(|cookie| cookie.value())
+}
+
+/// Instructs browser to forget session cookie
+pub fn remove_session_cookie(cookies: CookieJar, settings: &CookieSettings) -> CookieJar {
+    cookies.remove(settings.cookie(COOKIE_NAME, String::new()))
 }
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{query, SqliteExecutor};
+
+/// Role of user category is shared with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum MemberRole {
+    /// Only sees category and its words
+    Viewer,
+    /// Also adds and removes words
+    Editor,
+}
+
+/// What user may do with category. Each level allows everything that lower
+/// levels do.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryAccess {
+    Viewer,
+    Editor,
+    /// Also manages category itself and its members
+    Owner,
+}
+
+impl From<MemberRole> for CategoryAccess {
+    fn from(role: MemberRole) -> Self {
+        match role {
+            MemberRole::Viewer => CategoryAccess::Viewer,
+            MemberRole::Editor => CategoryAccess::Editor,
+        }
+    }
+}
+
+impl CategoryAccess {
+    /// Access of user to category owned by `owner_id`, where user has
+    /// `member_role` if any
+    pub fn resolve(
+        user_id: i64,
+        owner_id: i64,
+        member_role: Option<MemberRole>,
+    ) -> Option<CategoryAccess> {
+        if user_id == owner_id {
+            Some(CategoryAccess::Owner)
+        } else {
+            member_role.map(CategoryAccess::from)
+        }
+    }
+}
+
+/// Returns user's access to category, `None` if category doesn't exist or
+/// isn't shared with user
+pub async fn category_access(
+    exec: impl SqliteExecutor<'_>,
+    category_id: i64,
+    user_id: i64,
+) -> sqlx::Result<Option<CategoryAccess>> {
+    let category = query!(
+        r#"select c.user_id, m.role as "role?: MemberRole"
+        from categories c
+        left join category_members m on m.category_id = c.id and m.user_id = ?
+        where c.id = ?"#,
+        user_id,
+        category_id
+    )
+    .fetch_optional(exec)
+    .await?;
+
+    Ok(category.and_then(|c| CategoryAccess::resolve(user_id, c.user_id, c.role)))
+}
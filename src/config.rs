@@ -0,0 +1,197 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::auth::CookieSettings;
+
+/// Prefix of environment variables overriding configuration file
+pub const ENV_PREFIX: &str = "PSS_";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("can't read config file {0}: {1}")]
+    Read(PathBuf, #[source] std::io::Error),
+    #[error("can't parse config file {0}: {1}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+    #[error("invalid value of environment variable {0}: {1:?}")]
+    InvalidEnv(String, String),
+}
+
+/// Server configuration. Values are taken from defaults, then overridden by
+/// TOML file, then by `PSS_*` environment variables, then by command line.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub database_url: String,
+    pub pool_size: u32,
+    /// One of `error`, `warn`, `info`, `debug`, `trace`
+    pub log_level: String,
+    pub cookie: CookieSettings,
+    /// Serve HTTPS instead of HTTP when set
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM file with certificate chain
+    pub cert_path: PathBuf,
+    /// PEM file with private key
+    pub key_path: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 8080,
+            database_url: "development.sqlite".to_owned(),
+            pool_size: 5,
+            log_level: "info".to_owned(),
+            cookie: CookieSettings::default(),
+            tls: None,
+        }
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str, value: String) -> Result<T, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError::InvalidEnv(format!("{}{}", ENV_PREFIX, name), value))
+}
+
+impl Config {
+    /// Loads configuration from optional file and process environment
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let mut config = match path {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        config.apply_env(|name| std::env::var(format!("{}{}", ENV_PREFIX, name)).ok())?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let data =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_owned(), e))?;
+        toml::from_str(&data).map_err(|e| ConfigError::Parse(path.to_owned(), e))
+    }
+
+    /// Overrides values with variables returned by `var`, which gets
+    /// variable names without prefix
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        if let Some(v) = var("BIND_ADDRESS") {
+            self.bind_address = parse_env("BIND_ADDRESS", v)?;
+        }
+        if let Some(v) = var("PORT") {
+            self.port = parse_env("PORT", v)?;
+        }
+        if let Some(v) = var("DATABASE_URL") {
+            self.database_url = v;
+        }
+        if let Some(v) = var("POOL_SIZE") {
+            self.pool_size = parse_env("POOL_SIZE", v)?;
+        }
+        if let Some(v) = var("LOG_LEVEL") {
+            self.log_level = v;
+        }
+        if let Some(v) = var("COOKIE_PATH") {
+            self.cookie.path = v;
+        }
+        if let Some(v) = var("COOKIE_DOMAIN") {
+            self.cookie.domain = Some(v);
+        }
+        if let Some(v) = var("COOKIE_SECURE") {
+            self.cookie.secure = parse_env("COOKIE_SECURE", v)?;
+        }
+        match (var("TLS_CERT_PATH"), var("TLS_KEY_PATH")) {
+            (Some(cert_path), Some(key_path)) => {
+                self.tls = Some(TlsConfig {
+                    cert_path: cert_path.into(),
+                    key_path: key_path.into(),
+                })
+            }
+            (None, None) => {}
+            (Some(_), None) => {
+                return Err(ConfigError::InvalidEnv(
+                    format!("{}TLS_KEY_PATH", ENV_PREFIX),
+                    "".to_owned(),
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(ConfigError::InvalidEnv(
+                    format!("{}TLS_CERT_PATH", ENV_PREFIX),
+                    "".to_owned(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_address, self.port)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::Config;
+
+    #[test]
+    fn test_parse_config_file() {
+        let config: Config = toml::from_str(
+            r#"
+            bind_address = "0.0.0.0"
+            pool_size = 10
+
+            [cookie]
+            path = "/pss"
+            secure = true
+
+            [tls]
+            cert_path = "cert.pem"
+            key_path = "key.pem"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.socket_addr().to_string(), "0.0.0.0:8080");
+        assert_eq!(config.pool_size, 10);
+        assert_eq!(config.cookie.path, "/pss");
+        assert!(config.cookie.secure);
+        assert_eq!(config.database_url, "development.sqlite");
+        assert!(config.tls.is_some());
+    }
+
+    #[test]
+    fn test_apply_env() {
+        let vars = HashMap::from([
+            ("PORT", "9000"),
+            ("DATABASE_URL", "production.sqlite"),
+            ("COOKIE_DOMAIN", "example.com"),
+        ]);
+        let mut config = Config::default();
+        config
+            .apply_env(|name| vars.get(name).map(|v| v.to_string()))
+            .unwrap();
+
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.database_url, "production.sqlite");
+        assert_eq!(config.cookie.domain.as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn test_apply_env_invalid() {
+        let mut config = Config::default();
+        config
+            .apply_env(|name| (name == "PORT").then(|| "not a port".to_owned()))
+            .unwrap_err();
+    }
+}
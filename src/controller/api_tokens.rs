@@ -0,0 +1,116 @@
+use crate::api_data::{ApiTokenCreateRequest, ApiTokens, CreatedApiToken};
+use crate::api_tokens;
+use crate::auth::CurrentSession;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Path};
+use axum::Extension;
+use sqlx::SqlitePool;
+
+// Tokens are managed only with session cookie, so that leaked token can't be
+// used to issue more tokens
+
+pub async fn list_tokens(
+    Extension(pool): Extension<SqlitePool>,
+    session: CurrentSession,
+) -> ApiResult<Json<ApiTokens>> {
+    let tokens = api_tokens::list_tokens(&pool, session.user_id).await?;
+    Ok(Json(ApiTokens { tokens }))
+}
+
+pub async fn create_token(
+    Extension(pool): Extension<SqlitePool>,
+    session: CurrentSession,
+    Json(request): Json<ApiTokenCreateRequest>,
+) -> ApiResult<Json<CreatedApiToken>> {
+    if request.name.trim().is_empty() {
+        return Err(ApiError::Validation("Token name is empty".to_owned(), None));
+    }
+    let token =
+        api_tokens::create_token(&pool, session.user_id, &request.name, request.scope).await?;
+    Ok(Json(token))
+}
+
+pub async fn delete_token(
+    Extension(pool): Extension<SqlitePool>,
+    Path(token_id): Path<i64>,
+    session: CurrentSession,
+) -> ApiResult<()> {
+    if api_tokens::delete_token(&pool, session.user_id, token_id).await? {
+        Ok(())
+    } else {
+        Err(ApiError::NotFound("Token not found".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    use crate::{
+        api_tokens::{create_token, TokenScope},
+        app,
+        auth::CookieSettings,
+        test_utils::*,
+    };
+
+    fn create_category_request(secret: &str) -> Request<Body> {
+        Request::post("/api/v1/words")
+            .header(header::AUTHORIZATION, format!("Bearer {}", secret))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"name": "test"}"#))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_scopes() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let read_write = create_token(&pool, user, "rw", TokenScope::ReadWrite)
+            .await
+            .unwrap();
+        let read_only = create_token(&pool, user, "ro", TokenScope::ReadOnly)
+            .await
+            .unwrap();
+        let app = app(pool, CookieSettings::default());
+
+        // No CSRF token is needed with bearer token
+        let response = app
+            .clone()
+            .oneshot(create_category_request(&read_write.secret))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(create_category_request(&read_only.secret))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/words")
+                    .header(
+                        header::AUTHORIZATION,
+                        format!("Bearer {}", read_only.secret),
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(create_category_request("pss_invalid"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::api_data::{ArchiveImportReport, WordsArchive};
+use crate::auth::SessionUser;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::Json;
+use crate::word_lists::{self, WordListError};
+use axum::Extension;
+use sqlx::SqlitePool;
+
+/// Exports all categories and words of current user
+pub async fn export_archive(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<Json<WordsArchive>> {
+    Ok(Json(word_lists::export_archive(&pool, user_id).await?))
+}
+
+/// Creates new categories for current user from previously exported archive
+pub async fn import_archive(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+    Json(archive): Json<WordsArchive>,
+) -> ApiResult<Json<ArchiveImportReport>> {
+    match word_lists::import_archive(&pool, user_id, archive).await {
+        Ok(report) => Ok(Json(report)),
+        Err(WordListError::SqlError(e)) => Err(e.into()),
+        Err(e) => Err(ApiError::BadRequest(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::Extension;
+
+    use crate::{
+        api_data::WordsArchive, auth::SessionUser, controller::extract::Json, test_utils::*,
+    };
+
+    #[tokio::test]
+    async fn test_import_archive_unsupported_version() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+
+        super::import_archive(
+            Extension(pool),
+            SessionUser(user),
+            Json(WordsArchive {
+                version: 999,
+                categories: vec![],
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+    }
+}
@@ -1,75 +1,290 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
+    extract::ConnectInfo,
     headers::UserAgent,
-    response::{Html, IntoResponse, Redirect, Response, Result},
-    Extension, Form, TypedHeader,
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    Extension, TypedHeader,
 };
+use axum_extra::extract::CookieJar;
 use handlebars::Handlebars;
 use serde::Deserialize;
 use serde_json::json;
 use sqlx::SqlitePool;
 
-use crate::users::{authenticate_user_by_password, create_session};
+use crate::auth::CookieSettings;
+use crate::csrf::{csrf_cookie, csrf_cookie_token, new_csrf_token, tokens_match};
+use crate::login_throttle;
+use crate::users::{
+    authenticate_user_by_password, create_session, delete_session_by_secret, register_user,
+    UsersError,
+};
+
+use super::error::{ApiError, ApiResult};
+use super::extract::{Form, Query};
 
-use super::utils::InternalServerErrorResultExt;
+const MAX_USERNAME_LENGTH: usize = 50;
+const MIN_PASSWORD_LENGTH: usize = 8;
 
 #[derive(Deserialize)]
 pub struct LoginFormData {
     username: String,
     password: String,
+    csrf_token: String,
+}
+
+/// Reuses CSRF token from cookies or issues new one
+fn ensure_csrf_token(cookies: CookieJar, settings: &CookieSettings) -> (CookieJar, String) {
+    match csrf_cookie_token(&cookies) {
+        Some(token) => {
+            let token = token.to_owned();
+            (cookies, token)
+        }
+        None => {
+            let token = new_csrf_token();
+            (cookies.add(csrf_cookie(token.clone(), settings)), token)
+        }
+    }
+}
+
+pub async fn login_page(
+    Extension(handlebars): Extension<Arc<Handlebars<'_>>>,
+    Extension(cookie_settings): Extension<CookieSettings>,
+    cookies: CookieJar,
+) -> ApiResult<Response> {
+    let (cookies, csrf_token) = ensure_csrf_token(cookies, &cookie_settings);
+    Ok((
+        cookies,
+        Html(handlebars.render("login.hbs", &json!({ "csrf_token": csrf_token }))?),
+    )
+        .into_response())
 }
 
-pub async fn login_page(Extension(handlebars): Extension<Arc<Handlebars<'_>>>) -> Result<Response> {
-    Ok(Html(handlebars.render("login.hbs", &()).into_500()?).into_response())
+/// Logs user in and redirects to main page
+async fn start_session(
+    pool: &SqlitePool,
+    user_id: i64,
+    user_agent: &UserAgent,
+    cookies: CookieJar,
+    cookie_settings: &CookieSettings,
+) -> ApiResult<Response> {
+    let session_secret = create_session(pool, user_id, user_agent.as_str()).await?;
+    // CSRF token is rotated on login
+    let cookies = cookies
+        .add(crate::auth::session_cookie(session_secret, cookie_settings))
+        .add(csrf_cookie(new_csrf_token(), cookie_settings));
+    Ok((cookies, Redirect::to("/")).into_response())
 }
 
 pub async fn login_submit(
     Extension(pool): Extension<SqlitePool>,
     Extension(handlebars): Extension<Arc<Handlebars<'_>>>,
+    Extension(cookie_settings): Extension<CookieSettings>,
     TypedHeader(user_agent): TypedHeader<UserAgent>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    cookies: CookieJar,
     Form(form_data): Form<LoginFormData>,
-) -> Result<Response> {
-    let opt_user_id = authenticate_user_by_password(&pool, &form_data.username, form_data.password)
-        .await
-        .into_500()?;
+) -> ApiResult<Response> {
+    match csrf_cookie_token(&cookies) {
+        Some(token) if tokens_match(token, &form_data.csrf_token) => {}
+        _ => return Err(ApiError::Forbidden("Invalid CSRF token".to_owned())),
+    }
+
+    let ip = connect_info
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_default();
+
+    // Recorded before password verification, so that concurrent attempts are
+    // throttled too and locked out attempts don't cost argon2 run
+    if let Some(retry_after) =
+        login_throttle::record_attempt(&pool, &form_data.username, &ip).await?
+    {
+        let seconds = retry_after.whole_seconds() + 1;
+        let page = handlebars.render(
+            "login.hbs",
+            &json!({
+                "username": form_data.username,
+                "error": format!("Too many failed attempts, try again in {} s", seconds),
+                "csrf_token": form_data.csrf_token,
+            }),
+        )?;
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, seconds.to_string())],
+            Html(page),
+        )
+            .into_response());
+    }
+
+    let opt_user_id =
+        authenticate_user_by_password(&pool, &form_data.username, form_data.password).await?;
 
     match opt_user_id {
         Some(user_id) => {
-            let session_secret = create_session(&pool, user_id, user_agent.as_str())
-                .await
-                .into_500()?;
-            Ok((
-                crate::auth::session_cookie(session_secret),
-                Redirect::temporary("/"),
-            )
-                .into_response())
+            login_throttle::clear_failures(&pool, &form_data.username).await?;
+            start_session(&pool, user_id, &user_agent, cookies, &cookie_settings).await
         }
-        None => Ok(Html(
-            handlebars
-                .render(
-                    "login.hbs",
-                    &json!({"username": form_data.username, "error": "Invalid login"}),
-                )
-                .into_500()?,
-        )
+        None => Ok(Html(handlebars.render(
+            "login.hbs",
+            &json!({
+                "username": form_data.username,
+                "error": "Invalid login",
+                "csrf_token": form_data.csrf_token,
+            }),
+        )?)
         .into_response()),
     }
 }
 
+#[derive(Deserialize)]
+pub struct RegisterQuery {
+    code: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RegisterFormData {
+    invite_code: String,
+    username: String,
+    password: String,
+    csrf_token: String,
+}
+
+/// Invite code may be passed in link as `?code=`
+pub async fn register_page(
+    Extension(handlebars): Extension<Arc<Handlebars<'_>>>,
+    Extension(cookie_settings): Extension<CookieSettings>,
+    cookies: CookieJar,
+    Query(query): Query<RegisterQuery>,
+) -> ApiResult<Response> {
+    let (cookies, csrf_token) = ensure_csrf_token(cookies, &cookie_settings);
+    Ok((
+        cookies,
+        Html(handlebars.render(
+            "register.hbs",
+            &json!({ "csrf_token": csrf_token, "invite_code": query.code }),
+        )?),
+    )
+        .into_response())
+}
+
+fn registration_form_error(form_data: &RegisterFormData) -> Option<String> {
+    let username_length = form_data.username.chars().count();
+    if username_length == 0 || username_length > MAX_USERNAME_LENGTH {
+        Some(format!(
+            "Username must be from 1 to {} characters long",
+            MAX_USERNAME_LENGTH
+        ))
+    } else if form_data.username.trim() != form_data.username {
+        Some("Username must not start or end with spaces".to_owned())
+    } else if form_data.password.chars().count() < MIN_PASSWORD_LENGTH {
+        Some(format!(
+            "Password must be at least {} characters long",
+            MIN_PASSWORD_LENGTH
+        ))
+    } else {
+        None
+    }
+}
+
+/// Creates user with invite code and logs them in
+pub async fn register_submit(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(handlebars): Extension<Arc<Handlebars<'_>>>,
+    Extension(cookie_settings): Extension<CookieSettings>,
+    TypedHeader(user_agent): TypedHeader<UserAgent>,
+    cookies: CookieJar,
+    Form(form_data): Form<RegisterFormData>,
+) -> ApiResult<Response> {
+    match csrf_cookie_token(&cookies) {
+        Some(token) if tokens_match(token, &form_data.csrf_token) => {}
+        _ => return Err(ApiError::Forbidden("Invalid CSRF token".to_owned())),
+    }
+
+    let error = match registration_form_error(&form_data) {
+        Some(error) => error,
+        None => match register_user(
+            &pool,
+            &form_data.invite_code,
+            &form_data.username,
+            form_data.password.clone(),
+        )
+        .await
+        {
+            Ok(user_id) => {
+                return start_session(&pool, user_id, &user_agent, cookies, &cookie_settings).await
+            }
+            Err(UsersError::UsernameTaken(_)) => "Username is already taken".to_owned(),
+            Err(UsersError::InvalidInvite) => {
+                "Invite code is invalid, used up or expired".to_owned()
+            }
+            Err(e) => return Err(e.into()),
+        },
+    };
+
+    Ok(Html(handlebars.render(
+        "register.hbs",
+        &json!({
+            "invite_code": form_data.invite_code,
+            "username": form_data.username,
+            "error": error,
+            "csrf_token": form_data.csrf_token,
+        }),
+    )?)
+    .into_response())
+}
+
+#[derive(Deserialize)]
+pub struct LogoutFormData {
+    csrf_token: String,
+}
+
+/// Deletes current session and its cookie
+pub async fn logout(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(cookie_settings): Extension<CookieSettings>,
+    cookies: CookieJar,
+    Form(form_data): Form<LogoutFormData>,
+) -> ApiResult<Response> {
+    match csrf_cookie_token(&cookies) {
+        Some(token) if tokens_match(token, &form_data.csrf_token) => {}
+        _ => return Err(ApiError::Forbidden("Invalid CSRF token".to_owned())),
+    }
+
+    if let Some(secret) = crate::auth::session_secret(&cookies) {
+        delete_session_by_secret(&pool, secret).await?;
+    }
+    Ok((
+        crate::auth::remove_session_cookie(cookies, &cookie_settings),
+        Redirect::to("/auth/login"),
+    )
+        .into_response())
+}
+
 #[cfg(test)]
 mod test {
     use std::sync::Arc;
 
-    use axum::{http, Extension};
+    use axum::{body::Body, http, Extension};
+    use axum_extra::extract::CookieJar;
+    use tower::ServiceExt;
 
     use super::login_page;
-    use crate::make_handlebars;
+    use crate::{
+        app, auth::CookieSettings, invites::create_invite, login_throttle, make_handlebars,
+        test_utils::*,
+    };
 
     #[tokio::test]
     async fn test_login_page() {
         let handlebars = make_handlebars();
-        let response = login_page(Extension(Arc::new(handlebars))).await.unwrap();
+        let response = login_page(
+            Extension(Arc::new(handlebars)),
+            Extension(CookieSettings::default()),
+            CookieJar::new(),
+        )
+        .await
+        .unwrap();
         assert_eq!(response.status(), http::StatusCode::OK);
         assert_eq!(
             response
@@ -80,5 +295,88 @@ mod test {
                 .unwrap(),
             "text/html; charset=utf-8"
         );
+        assert!(response
+            .headers()
+            .get(http::header::SET_COOKIE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("pss_csrf="));
+    }
+
+    #[tokio::test]
+    async fn test_login_locked_out() {
+        let pool = test_database_pool().await;
+        add_test_user(&pool, "user").await;
+        for _ in 0..login_throttle::FREE_ATTEMPTS_PER_USERNAME {
+            login_throttle::record_attempt(&pool, "user", "")
+                .await
+                .unwrap();
+        }
+
+        let response = app(pool, CookieSettings::default())
+            .oneshot(
+                http::Request::post("/auth/login")
+                    .header(http::header::USER_AGENT, "test")
+                    .header(http::header::COOKIE, "pss_csrf=token")
+                    .header(
+                        http::header::CONTENT_TYPE,
+                        "application/x-www-form-urlencoded",
+                    )
+                    .body(Body::from("username=user&password=123&csrf_token=token"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(http::header::RETRY_AFTER));
+    }
+
+    fn register_request(body: String) -> http::Request<Body> {
+        http::Request::post("/auth/register")
+            .header(http::header::USER_AGENT, "test")
+            .header(http::header::COOKIE, "pss_csrf=token")
+            .header(
+                http::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_register() {
+        let pool = test_database_pool().await;
+        add_test_user(&pool, "taken").await;
+        let code = create_invite(&pool, 1, None).await.unwrap();
+        let app = app(pool, CookieSettings::default());
+
+        let response = app
+            .clone()
+            .oneshot(register_request(format!(
+                "invite_code={}&username=taken&password=password&csrf_token=token",
+                code
+            )))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("Username is already taken"));
+
+        let response = app
+            .oneshot(register_request(format!(
+                "invite_code={}&username=user&password=password&csrf_token=token",
+                code
+            )))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::SEE_OTHER);
+        assert!(response
+            .headers()
+            .get_all(http::header::SET_COOKIE)
+            .iter()
+            .any(|c| c.to_str().unwrap().starts_with("pss_session=")));
     }
 }
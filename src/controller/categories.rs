@@ -1,64 +1,172 @@
-use crate::api_data::{Categories, Category, CategoryCreateRequest, CategoryUpdateRequest};
+use crate::api_data::{
+    Categories, Category, CategoryCreateRequest, CategoryDeleteReport, CategoryReorderRequest,
+    CategoryUpdateRequest,
+};
 use crate::auth::SessionUser;
-use crate::controller::utils::InternalServerErrorResultExt;
-use axum::extract::Path;
-use axum::http::StatusCode;
-use axum::response::Result;
-use axum::{Extension, Json};
-use futures::stream::StreamExt;
+use crate::category_members::{CategoryAccess, MemberRole};
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Path, Query};
+use crate::controller::words::check_words_unused;
+use crate::validation::{normalize_category_name, validate_field};
+use axum::Extension;
+use serde::Deserialize;
+use serde_json::json;
 use sqlx::types::time::OffsetDateTime;
 use sqlx::{query, query_as, query_scalar, SqlitePool};
 
-pub const SAMPLE_WORDS_COUNT: i32 = 5;
+pub const SAMPLE_WORDS_COUNT: u32 = 5;
+pub const MAX_SAMPLE_WORDS_COUNT: u32 = 50;
+
+/// Which words are shown as category's samples
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleOrder {
+    /// Earliest added words, which don't change as category grows
+    #[default]
+    First,
+    /// Latest added words
+    Recent,
+    /// Different words on every request
+    Random,
+}
+
+impl SampleOrder {
+    fn as_str(self) -> &'static str {
+        match self {
+            SampleOrder::First => "first",
+            SampleOrder::Recent => "recent",
+            SampleOrder::Random => "random",
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct CategoryListQuery {
+    #[serde(default)]
+    samples: SampleOrder,
+    sample_count: Option<u32>,
+    #[serde(default)]
+    include_archived: bool,
+}
 
-/// Basic information about category, including word count. Intermediate structure for outputting.
-struct CategoryBasic {
+#[derive(sqlx::FromRow)]
+struct CategoryRow {
     id: i64,
     name: Option<String>,
+    position: i64,
+    pinned: bool,
+    owner_id: i64,
+    member_role: Option<MemberRole>,
+    archived_at: Option<OffsetDateTime>,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
     num_words: i64,
+    sample_word: Option<String>,
 }
 
-async fn build_category(
+/// Fetches categories user owns or is member of in user-defined order, or
+/// only one of them if `category_id` is given, along with word counts and
+/// samples in single query.
+///
+/// Not checked at compile time, since sqlx's query analysis doesn't finish
+/// on this query.
+async fn fetch_categories(
     exec: impl sqlx::SqliteExecutor<'_>,
-    category: CategoryBasic,
-) -> sqlx::Result<Category> {
-    let sample_words = query_scalar!(
-        "select word from words where category_id = ? limit ?",
-        category.id,
-        SAMPLE_WORDS_COUNT
+    user_id: i64,
+    category_id: Option<i64>,
+    include_archived: bool,
+    samples: SampleOrder,
+    sample_count: u32,
+) -> sqlx::Result<Vec<Category>> {
+    let order = samples.as_str();
+    let rows: Vec<CategoryRow> = query_as(
+        "select c.id, c.name, c.position, c.pinned, c.user_id as owner_id,
+          m.role as member_role, c.archived_at, c.created_at, c.updated_at,
+          (select count(*) from words where category_id = c.id) as num_words,
+          s.word as sample_word
+        from categories c
+        left join (
+          select category_id, word, row_number() over (
+            partition by category_id
+            order by case when ?1 = 'random' then random() end,
+              case when ?1 = 'recent' then created_at end desc,
+              case when ?1 = 'recent' then id end desc,
+              created_at, id
+          ) as position
+          from words
+          -- Rank only words of listed categories
+          where category_id in (
+            select id from categories where user_id = ?2
+            union all
+            select category_id from category_members where user_id = ?2
+          ) and (?3 is null or category_id = ?3)
+        ) s on s.category_id = c.id and s.position <= ?4
+        left join category_members m on m.category_id = c.id and m.user_id = ?2
+        where (c.user_id = ?2 or m.user_id is not null)
+          and (?3 is null or c.id = ?3) and (?5 or c.archived_at is null)
+        order by c.pinned desc, c.position, c.id, s.position",
     )
+    .bind(order)
+    .bind(user_id)
+    .bind(category_id)
+    .bind(sample_count)
+    .bind(include_archived)
     .fetch_all(exec)
     .await?;
 
-    Ok(Category {
-        id: category.id,
-        name: category.name,
-        num_words: category.num_words,
-        sample_words,
-    })
+    let mut categories: Vec<Category> = Vec::new();
+    for row in rows {
+        match categories.last_mut() {
+            Some(category) if category.id == row.id => {}
+            _ => categories.push(Category {
+                id: row.id,
+                name: row.name,
+                num_words: row.num_words,
+                sample_words: vec![],
+                position: row.position,
+                pinned: row.pinned,
+                archived_at: row.archived_at,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+                access: CategoryAccess::resolve(user_id, row.owner_id, row.member_role)
+                    .expect("only accessible categories are fetched"),
+            }),
+        }
+        if let Some(word) = row.sample_word {
+            categories
+                .last_mut()
+                .expect("category just added")
+                .sample_words
+                .push(word);
+        }
+    }
+    Ok(categories)
+}
+
+/// Category name is optional, but must be valid when given
+fn validate_category_name(name: Option<String>) -> ApiResult<Option<String>> {
+    name.map(|name| validate_field("name", normalize_category_name(&name)))
+        .transpose()
 }
 
 pub async fn list_categories(
     Extension(pool): Extension<SqlitePool>,
     SessionUser(user_id): SessionUser,
-) -> Result<Json<Categories>> {
-    let mut categories_basic = query_as!(
-        CategoryBasic,
-        r#"select categories.id as "id!", categories.name, cast(count(words.id) as integer) as num_words
-        from categories
-        left join words on categories.id = words.category_id
-        where categories.user_id = ?
-        group by categories.id"#,
+    Query(list_query): Query<CategoryListQuery>,
+) -> ApiResult<Json<Categories>> {
+    let sample_count = list_query
+        .sample_count
+        .unwrap_or(SAMPLE_WORDS_COUNT)
+        .min(MAX_SAMPLE_WORDS_COUNT);
+    let categories = fetch_categories(
+        &pool,
         user_id,
+        None,
+        list_query.include_archived,
+        list_query.samples,
+        sample_count,
     )
-    .fetch(&pool);
-
-    let mut categories: Vec<Category> = Vec::with_capacity(SAMPLE_WORDS_COUNT as usize);
-
-    while let Some(category_r) = categories_basic.next().await {
-        let category = category_r.into_500()?;
-        categories.push(build_category(&pool, category).await.into_500()?);
-    }
+    .await?;
 
     Ok(Json(Categories { categories }))
 }
@@ -67,26 +175,34 @@ pub async fn create_category(
     Extension(pool): Extension<SqlitePool>,
     SessionUser(user_id): SessionUser,
     Json(category_create): Json<CategoryCreateRequest>,
-) -> Result<Json<Category>> {
+) -> ApiResult<Json<Category>> {
+    let name = validate_category_name(category_create.name)?;
     let current_time = OffsetDateTime::now_utc();
-    let new_category_id = query!(
-        "insert into categories(user_id, name, created_at, updated_at)
-        values(?, ?, ?, ?)",
+    // New category goes last
+    let new_category = query!(
+        r#"insert into categories(user_id, name, position, created_at, updated_at)
+        values(?, ?, (select coalesce(max(position) + 1, 0) from categories where user_id = ?), ?, ?)
+        returning id, position as "position!""#,
+        user_id,
+        name,
         user_id,
-        category_create.name,
         current_time,
         current_time
     )
-    .execute(&pool)
-    .await
-    .into_500()?
-    .last_insert_rowid();
+    .fetch_one(&pool)
+    .await?;
 
     Ok(Json(Category {
-        id: new_category_id,
-        name: category_create.name,
+        id: new_category.id,
+        name,
         num_words: 0,
         sample_words: vec![],
+        position: new_category.position,
+        pinned: false,
+        archived_at: None,
+        created_at: current_time,
+        updated_at: current_time,
+        access: CategoryAccess::Owner,
     }))
 }
 
@@ -95,79 +211,307 @@ pub async fn update_category(
     Path(category_id): Path<i64>,
     SessionUser(user_id): SessionUser,
     Json(category_update): Json<CategoryUpdateRequest>,
-) -> Result<Json<Category>> {
+) -> ApiResult<Json<Category>> {
+    let name = category_update
+        .name
+        .map(validate_category_name)
+        .transpose()?;
+    let name_given = name.is_some();
+    let name = name.flatten();
+    let current_time = OffsetDateTime::now_utc();
     let categories_updated = query!(
-        "update categories set name = ? where id = ? and user_id = ?",
-        category_update.name,
+        "update categories set
+          name = case when ? then ? else name end,
+          pinned = coalesce(?, pinned),
+          archived_at = case ? when 1 then coalesce(archived_at, ?) when 0 then null else archived_at end,
+          updated_at = ?
+        where id = ? and user_id = ?",
+        name_given,
+        name,
+        category_update.pinned,
+        category_update.archived,
+        current_time,
+        current_time,
         category_id,
         user_id,
     )
     .execute(&pool)
-    .await
-    .into_500()?
+    .await?
     .rows_affected();
 
     if categories_updated == 0 {
-        return Err((StatusCode::NOT_FOUND, "Category not found").into());
+        return Err(ApiError::NotFound("Category not found".to_owned()));
     }
 
-    let category = query_as!(
-        CategoryBasic,
-        "select categories.id, categories.name, cast(count(words.id) as integer) as num_words
-        from categories
-        left join words on categories.id = words.category_id
-        where categories.id = ? group by categories.id",
+    // Category might have been deleted concurrently
+    let category = fetch_categories(
+        &pool,
+        user_id,
+        Some(category_id),
+        true,
+        SampleOrder::default(),
+        SAMPLE_WORDS_COUNT,
+    )
+    .await?
+    .pop()
+    .ok_or_else(|| ApiError::NotFound("Category not found".to_owned()))?;
+    Ok(Json(category))
+}
+
+/// Moves listed categories to the beginning of user-defined order, in given
+/// order. Returns categories in new order.
+pub async fn reorder_categories(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+    Json(reorder): Json<CategoryReorderRequest>,
+) -> ApiResult<Json<Categories>> {
+    let mut unique_ids = reorder.category_ids.clone();
+    unique_ids.sort_unstable();
+    unique_ids.dedup();
+    if unique_ids.len() != reorder.category_ids.len() {
+        return Err(ApiError::BadRequest("Duplicate category ids".to_owned()));
+    }
+
+    let mut transaction = pool.begin().await?;
+    let current_ids = query_scalar!(
+        r#"select id as "id!" from categories where user_id = ? order by position, id"#,
+        user_id
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+    if !reorder
+        .category_ids
+        .iter()
+        .all(|id| current_ids.contains(id))
+    {
+        return Err(ApiError::NotFound(
+            "Some of categories not found".to_owned(),
+        ));
+    }
+
+    let rest = current_ids
+        .into_iter()
+        .filter(|id| !reorder.category_ids.contains(id));
+    for (position, id) in reorder.category_ids.iter().copied().chain(rest).enumerate() {
+        let position = position as i64;
+        query!(
+            "update categories set position = ? where id = ?",
+            position,
+            id
+        )
+        .execute(&mut transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    let categories = fetch_categories(
+        &pool,
+        user_id,
+        None,
+        false,
+        SampleOrder::default(),
+        SAMPLE_WORDS_COUNT,
+    )
+    .await?;
+    Ok(Json(Categories { categories }))
+}
+
+/// What happens to words of category being deleted
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryDeleteMode {
+    /// Delete only empty category
+    Refuse,
+    /// Delete category along with its words, unless some of them are used
+    /// in games
+    Cascade,
+    /// Move words to category given by `move_to`
+    Move,
+}
+
+#[derive(Deserialize, Default)]
+pub struct CategoryDeleteQuery {
+    mode: Option<CategoryDeleteMode>,
+    move_to: Option<i64>,
+}
+
+impl CategoryDeleteQuery {
+    /// Mode with target category for moving. Giving `move_to` alone implies
+    /// moving, otherwise only empty categories are deleted.
+    fn mode(&self) -> ApiResult<(CategoryDeleteMode, Option<i64>)> {
+        match (self.mode, self.move_to) {
+            (None, None) => Ok((CategoryDeleteMode::Refuse, None)),
+            (None | Some(CategoryDeleteMode::Move), Some(target)) => {
+                Ok((CategoryDeleteMode::Move, Some(target)))
+            }
+            (Some(CategoryDeleteMode::Move), None) => Err(ApiError::BadRequest(
+                "Moving words requires move_to parameter".to_owned(),
+            )),
+            (Some(mode), None) => Ok((mode, None)),
+            (Some(_), Some(_)) => Err(ApiError::BadRequest(
+                "move_to parameter is only allowed with move mode".to_owned(),
+            )),
+        }
+    }
+}
+
+/// Moves words of category to target one. Words already present in target
+/// category aren't moved, instead their uses in games and compositions are
+/// transferred to existing words. Returns number of moved words.
+async fn move_category_words(
+    conn: &mut sqlx::SqliteConnection,
+    category_id: i64,
+    target_category_id: i64,
+) -> sqlx::Result<u64> {
+    let current_time = OffsetDateTime::now_utc();
+
+    query!(
+        "update or ignore game_words set word_id = (
+          select target.id from words source
+          join words target on target.word = source.word and target.category_id = ?
+          where source.id = game_words.word_id
+        )
+        where word_id in (
+          select source.id from words source
+          join words target on target.word = source.word and target.category_id = ?
+          where source.category_id = ?
+        )",
+        target_category_id,
+        target_category_id,
         category_id
     )
-    .fetch_one(&pool)
-    .await
-    .into_500()?;
+    .execute(&mut *conn)
+    .await?;
+    query!(
+        "update or ignore composition_words set word_id = (
+          select target.id from words source
+          join words target on target.word = source.word and target.category_id = ?
+          where source.id = composition_words.word_id
+        )
+        where word_id in (
+          select source.id from words source
+          join words target on target.word = source.word and target.category_id = ?
+          where source.category_id = ?
+        )",
+        target_category_id,
+        target_category_id,
+        category_id
+    )
+    .execute(&mut *conn)
+    .await?;
 
-    Ok(Json(build_category(&pool, category).await.into_500()?))
+    let words_moved = query!(
+        "update words set category_id = ?, updated_at = ?
+        where category_id = ?
+          and word not in (select word from words where category_id = ?)",
+        target_category_id,
+        current_time,
+        category_id,
+        target_category_id
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    Ok(words_moved)
 }
 
+/// Deletes category. Depending on mode its words are deleted too, moved to
+/// other category, or deletion is refused with 409 if there are any. Words
+/// used in games are never deleted, deletion is refused with 409 instead.
 pub async fn delete_category(
     Extension(pool): Extension<SqlitePool>,
     Path(category_id): Path<i64>,
     SessionUser(user_id): SessionUser,
-) -> Result<()> {
-    // FIXME: refine logic of categories that can't be deleted
-
-    let num_deleted = query!(
-        "with deletable_categories as (
-          select categories.id from categories
-          left join words on words.category_id = categories.id
-          group by categories.id having count(words.id) = 0
-        )
-        delete from categories where id = ?
-          and exists (
-            select * from deletable_categories where deletable_categories.id = id
-          ) and user_id = ?",
+    Query(delete_query): Query<CategoryDeleteQuery>,
+) -> ApiResult<Json<CategoryDeleteReport>> {
+    let (mode, move_to) = delete_query.mode()?;
+
+    let mut transaction = pool.begin().await?;
+    if query_scalar!(
+        "select id from categories where id = ? and user_id = ?",
         category_id,
-        user_id,
+        user_id
     )
-    .execute(&pool)
-    .await
-    .into_500()?
-    .rows_affected();
+    .fetch_optional(&mut transaction)
+    .await?
+    .is_none()
+    {
+        return Err(ApiError::NotFound("Category not found".to_owned()));
+    }
+
+    let mut report = CategoryDeleteReport {
+        words_deleted: 0,
+        words_moved: 0,
+    };
+    if let Some(target_category_id) = move_to {
+        if target_category_id == category_id {
+            return Err(ApiError::BadRequest(
+                "Can't move words to category being deleted".to_owned(),
+            ));
+        }
+        if query_scalar!(
+            "select id from categories where id = ? and user_id = ?",
+            target_category_id,
+            user_id
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .is_none()
+        {
+            return Err(ApiError::NotFound("Target category not found".to_owned()));
+        }
+        report.words_moved =
+            move_category_words(&mut transaction, category_id, target_category_id).await?;
+    }
 
-    if num_deleted == 1 {
-        Ok(())
-    } else {
-        Err((StatusCode::NOT_FOUND, "Category not found").into())
+    let words_count = query_scalar!(
+        "select count(*) from words where category_id = ?",
+        category_id
+    )
+    .fetch_one(&mut transaction)
+    .await?;
+    if mode == CategoryDeleteMode::Refuse && words_count > 0 {
+        return Err(ApiError::Conflict(
+            "Category is not empty".to_owned(),
+            Some(json!({ "words_count": words_count })),
+        ));
     }
+    // Remaining words are deleted by cascade. Words drawn in games, including
+    // duplicates whose uses couldn't be transferred when moving, are kept.
+    let remaining_ids = serde_json::to_string(
+        &query_scalar!(
+            r#"select id as "id!" from words where category_id = ?"#,
+            category_id
+        )
+        .fetch_all(&mut transaction)
+        .await?,
+    )?;
+    check_words_unused(&mut transaction, &remaining_ids).await?;
+    report.words_deleted = words_count as u64;
+
+    query!("delete from categories where id = ?", category_id)
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok(Json(report))
 }
 
 #[cfg(test)]
 mod test {
     use crate::{
-        api_data::{CategoryCreateRequest, CategoryUpdateRequest},
+        api_data::{CategoryCreateRequest, CategoryReorderRequest, CategoryUpdateRequest},
         auth::SessionUser,
+        controller::error::ApiError,
+        controller::extract::{Json, Path, Query},
         test_utils::*,
     };
-    use axum::{extract::Path, Extension, Json};
+    use axum::http::StatusCode;
+    use axum::Extension;
     use sqlx::query_scalar;
 
+    use super::{CategoryDeleteMode, CategoryDeleteQuery, CategoryListQuery, SampleOrder};
+
     use crate::api_data::Categories;
 
     #[tokio::test]
@@ -180,10 +524,13 @@ mod test {
         }
         add_test_category(&pool, user).await;
 
-        let Json(Categories { categories }) =
-            super::list_categories(Extension(pool.clone()), SessionUser(user))
-                .await
-                .expect("successful response with list of categories");
+        let Json(Categories { categories }) = super::list_categories(
+            Extension(pool.clone()),
+            SessionUser(user),
+            Query(CategoryListQuery::default()),
+        )
+        .await
+        .expect("successful response with list of categories");
 
         assert_eq!(categories.len(), 2);
         let long_category = categories
@@ -197,17 +544,62 @@ mod test {
         assert!(long_category
             .sample_words
             .iter()
-            .all(|sample_word| sample_word.len() > 0));
+            .all(|sample_word| !sample_word.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_list_categories_samples() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category_id = add_test_category(&pool, user).await;
+        for word in ["сыч", "сова", "сипуха"] {
+            add_test_named_word(&pool, category_id, word).await;
+        }
+
+        for (samples, expected) in [
+            (SampleOrder::First, vec!["сыч", "сова"]),
+            (SampleOrder::Recent, vec!["сипуха", "сова"]),
+        ] {
+            let Json(Categories { categories }) = super::list_categories(
+                Extension(pool.clone()),
+                SessionUser(user),
+                Query(CategoryListQuery {
+                    samples,
+                    sample_count: Some(2),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .expect("successful response with list of categories");
+            assert_eq!(categories[0].num_words, 3);
+            assert_eq!(categories[0].sample_words, expected);
+        }
+
+        let Json(Categories { categories }) = super::list_categories(
+            Extension(pool.clone()),
+            SessionUser(user),
+            Query(CategoryListQuery {
+                samples: SampleOrder::Random,
+                sample_count: Some(10),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect("successful response with list of categories");
+        assert_eq!(categories[0].sample_words.len(), 3);
     }
 
     #[tokio::test]
     async fn test_list_categories_empty() {
         let pool = test_database_pool().await;
         let user = add_test_user(&pool, "user").await;
-        let Json(Categories { categories }) =
-            super::list_categories(Extension(pool.clone()), SessionUser(user))
-                .await
-                .expect("successful response with list of categories");
+        let Json(Categories { categories }) = super::list_categories(
+            Extension(pool.clone()),
+            SessionUser(user),
+            Query(CategoryListQuery::default()),
+        )
+        .await
+        .expect("successful response with list of categories");
         assert!(categories.is_empty());
     }
 
@@ -247,6 +639,22 @@ mod test {
         assert!(category.id > 0);
     }
 
+    #[tokio::test]
+    async fn test_create_category_invalid_name() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+
+        super::create_category(
+            Extension(pool.clone()),
+            SessionUser(user),
+            Json(CategoryCreateRequest {
+                name: Some("   ".to_owned()),
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+    }
+
     #[tokio::test]
     async fn test_update_category_basic() {
         let pool = test_database_pool().await;
@@ -259,7 +667,8 @@ mod test {
             Path(category_id),
             SessionUser(user_id),
             Json(CategoryUpdateRequest {
-                name: Some("foo".to_owned()),
+                name: Some(Some("foo".to_owned())),
+                ..Default::default()
             }),
         )
         .await
@@ -269,19 +678,113 @@ mod test {
         assert_eq!(category.sample_words.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_category_order_pin_archive() {
+        let pool = test_database_pool().await;
+        let user_id = add_test_user(&pool, "user").await;
+        let mut ids = vec![];
+        for name in ["a", "b", "c", "d"] {
+            let Json(category) = super::create_category(
+                Extension(pool.clone()),
+                SessionUser(user_id),
+                Json(CategoryCreateRequest {
+                    name: Some(name.to_owned()),
+                }),
+            )
+            .await
+            .expect("successful response");
+            ids.push(category.id);
+        }
+        let [a, b, c, d] = ids[..] else {
+            unreachable!()
+        };
+
+        let Json(Categories { categories }) = super::reorder_categories(
+            Extension(pool.clone()),
+            SessionUser(user_id),
+            Json(CategoryReorderRequest {
+                category_ids: vec![c, a],
+            }),
+        )
+        .await
+        .expect("successful response");
+        let order: Vec<_> = categories.iter().map(|c| c.id).collect();
+        assert_eq!(order, vec![c, a, b, d]);
+
+        for (category_id, update) in [
+            (
+                d,
+                CategoryUpdateRequest {
+                    pinned: Some(true),
+                    ..Default::default()
+                },
+            ),
+            (
+                a,
+                CategoryUpdateRequest {
+                    archived: Some(true),
+                    ..Default::default()
+                },
+            ),
+        ] {
+            let Json(category) = super::update_category(
+                Extension(pool.clone()),
+                Path(category_id),
+                SessionUser(user_id),
+                Json(update),
+            )
+            .await
+            .expect("successful response");
+            assert_eq!(category.id, category_id);
+        }
+
+        let list = |include_archived| {
+            super::list_categories(
+                Extension(pool.clone()),
+                SessionUser(user_id),
+                Query(CategoryListQuery {
+                    include_archived,
+                    ..Default::default()
+                }),
+            )
+        };
+        let Json(Categories { categories }) = list(false).await.expect("successful response");
+        let order: Vec<_> = categories.iter().map(|c| c.id).collect();
+        assert_eq!(order, vec![d, c, b]);
+        // Name is left unchanged
+        assert_eq!(categories[0].name.as_deref(), Some("d"));
+
+        let Json(Categories { categories }) = list(true).await.expect("successful response");
+        let archived = categories.iter().find(|c| c.id == a).unwrap();
+        assert!(archived.archived_at.is_some());
+
+        // Foreign and duplicate ids are rejected
+        for category_ids in [vec![a, a], vec![a, 1000]] {
+            super::reorder_categories(
+                Extension(pool.clone()),
+                SessionUser(user_id),
+                Json(CategoryReorderRequest { category_ids }),
+            )
+            .await
+            .expect_err("unsuccessful response");
+        }
+    }
+
     #[tokio::test]
     async fn test_delete_category_basic() {
         let pool = test_database_pool().await;
         let user_id = add_test_user(&pool, "user").await;
         let category_id = add_test_category(&pool, user_id).await;
 
-        super::delete_category(
+        let Json(report) = super::delete_category(
             Extension(pool.clone()),
             Path(category_id),
             SessionUser(user_id),
+            Query(CategoryDeleteQuery::default()),
         )
         .await
         .expect("successful response");
+        assert_eq!(report.words_deleted, 0);
 
         let has_category =
             query_scalar!("select count(*) from categories where id = ?", category_id)
@@ -295,8 +798,163 @@ mod test {
     async fn test_delete_category_not_found() {
         let pool = test_database_pool().await;
         let user_id = add_test_user(&pool, "user").await;
-        super::delete_category(Extension(pool.clone()), Path(1), SessionUser(user_id))
+        super::delete_category(
+            Extension(pool.clone()),
+            Path(1),
+            SessionUser(user_id),
+            Query(CategoryDeleteQuery::default()),
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_delete_category_refuse_not_empty() {
+        let pool = test_database_pool().await;
+        let user_id = add_test_user(&pool, "user").await;
+        let category_id = add_test_category(&pool, user_id).await;
+        add_test_word(&pool, category_id).await;
+        add_test_word(&pool, category_id).await;
+
+        let error = super::delete_category(
+            Extension(pool.clone()),
+            Path(category_id),
+            SessionUser(user_id),
+            Query(CategoryDeleteQuery::default()),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        assert_eq!(error.status(), StatusCode::CONFLICT);
+        let ApiError::Conflict(_, Some(details)) = error else {
+            panic!("conflict with details expected");
+        };
+        assert_eq!(details["words_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_delete_category_cascade() {
+        let pool = test_database_pool().await;
+        let user_id = add_test_user(&pool, "user").await;
+        let category_id = add_test_category(&pool, user_id).await;
+        add_test_word(&pool, category_id).await;
+
+        let Json(report) = super::delete_category(
+            Extension(pool.clone()),
+            Path(category_id),
+            SessionUser(user_id),
+            Query(CategoryDeleteQuery {
+                mode: Some(CategoryDeleteMode::Cascade),
+                move_to: None,
+            }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(report.words_deleted, 1);
+
+        let words_count = query_scalar!("select count(*) from words")
+            .fetch_one(&pool)
             .await
-            .unwrap_err();
+            .unwrap();
+        assert_eq!(words_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_delete_category_cascade_words_in_game() {
+        let pool = test_database_pool().await;
+        let user_id = add_test_user(&pool, "user").await;
+        let other_user_id = add_test_user(&pool, "other").await;
+        let category_id = add_test_category(&pool, user_id).await;
+        add_test_word(&pool, category_id).await;
+        let word_id = add_test_word(&pool, category_id).await;
+        let game_id = add_test_game(&pool, user_id, other_user_id).await;
+        add_test_game_word(&pool, game_id, word_id).await;
+
+        let error = super::delete_category(
+            Extension(pool.clone()),
+            Path(category_id),
+            SessionUser(user_id),
+            Query(CategoryDeleteQuery {
+                mode: Some(CategoryDeleteMode::Cascade),
+                move_to: None,
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        let ApiError::Conflict(_, Some(details)) = error else {
+            panic!("conflict with details expected");
+        };
+        assert_eq!(details["game_ids"], serde_json::json!([game_id]));
+
+        let words_count = query_scalar!(
+            "select count(*) from words where category_id = ?",
+            category_id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(words_count, 2);
+        let game_word_id =
+            query_scalar!("select word_id from game_words where game_id = ?", game_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(game_word_id, word_id);
+    }
+
+    #[tokio::test]
+    async fn test_delete_category_move_to() {
+        let pool = test_database_pool().await;
+        let user_id = add_test_user(&pool, "user").await;
+        let other_user_id = add_test_user(&pool, "other").await;
+        let category_id = add_test_category(&pool, user_id).await;
+        let target_id = add_test_category(&pool, user_id).await;
+        add_test_named_word(&pool, category_id, "сова").await;
+        let duplicate_id = add_test_named_word(&pool, category_id, "сыч").await;
+        let existing_id = add_test_named_word(&pool, target_id, "сыч").await;
+        let game_id = add_test_game(&pool, user_id, other_user_id).await;
+        add_test_game_word(&pool, game_id, duplicate_id).await;
+
+        // Foreign target category
+        let foreign_id = add_test_category(&pool, other_user_id).await;
+        super::delete_category(
+            Extension(pool.clone()),
+            Path(category_id),
+            SessionUser(user_id),
+            Query(CategoryDeleteQuery {
+                mode: None,
+                move_to: Some(foreign_id),
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+
+        let Json(report) = super::delete_category(
+            Extension(pool.clone()),
+            Path(category_id),
+            SessionUser(user_id),
+            Query(CategoryDeleteQuery {
+                mode: None,
+                move_to: Some(target_id),
+            }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(report.words_moved, 1);
+        assert_eq!(report.words_deleted, 1);
+
+        let target_words_count = query_scalar!(
+            "select count(*) from words where category_id = ?",
+            target_id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(target_words_count, 2);
+        let game_word_id =
+            query_scalar!("select word_id from game_words where game_id = ?", game_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(game_word_id, existing_id);
     }
 }
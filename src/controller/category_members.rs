@@ -0,0 +1,299 @@
+use crate::api_data::{CategoryMember, CategoryMemberAddRequest, CategoryMembers};
+use crate::auth::SessionUser;
+use crate::category_members::{category_access, CategoryAccess, MemberRole};
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Path};
+use crate::users::user_by_name;
+use axum::Extension;
+use sqlx::types::time::OffsetDateTime;
+use sqlx::{query, query_as, SqlitePool};
+
+/// Checks that user has at least `required` access to category. Categories
+/// not shared with user are reported as not found.
+pub async fn require_category_access(
+    exec: impl sqlx::SqliteExecutor<'_>,
+    category_id: i64,
+    user_id: i64,
+    required: CategoryAccess,
+) -> ApiResult<CategoryAccess> {
+    match category_access(exec, category_id, user_id).await? {
+        None => Err(ApiError::NotFound("Category not found".to_owned())),
+        Some(access) if access < required => Err(ApiError::Forbidden(
+            "Not enough access to category".to_owned(),
+        )),
+        Some(access) => Ok(access),
+    }
+}
+
+pub async fn list_members(
+    Extension(pool): Extension<SqlitePool>,
+    Path(category_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<Json<CategoryMembers>> {
+    require_category_access(&pool, category_id, user_id, CategoryAccess::Viewer).await?;
+
+    let members = query_as!(
+        CategoryMember,
+        r#"select u.id as "user_id!", u.name as username, m.role as "role: MemberRole",
+          m.created_at as "created_at: OffsetDateTime"
+        from category_members m
+        join users u on u.id = m.user_id
+        where m.category_id = ?
+        order by u.name"#,
+        category_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(CategoryMembers { members }))
+}
+
+/// Shares category with user, or changes role of existing member
+pub async fn add_member(
+    Extension(pool): Extension<SqlitePool>,
+    Path(category_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+    Json(request): Json<CategoryMemberAddRequest>,
+) -> ApiResult<Json<CategoryMember>> {
+    require_category_access(&pool, category_id, user_id, CategoryAccess::Owner).await?;
+    let member_id = user_by_name(&pool, &request.username)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_owned()))?;
+    if member_id == user_id {
+        return Err(ApiError::BadRequest(
+            "Category owner can't be its member".to_owned(),
+        ));
+    }
+
+    let current_time = OffsetDateTime::now_utc();
+    let member = query_as!(
+        CategoryMember,
+        r#"insert into category_members (category_id, user_id, role, created_at)
+        values (?, ?, ?, ?)
+        on conflict (category_id, user_id) do update set role = excluded.role
+        returning user_id as "user_id!", ? as "username!: String", role as "role!: MemberRole",
+          created_at as "created_at!: OffsetDateTime""#,
+        category_id,
+        member_id,
+        request.role,
+        current_time,
+        request.username
+    )
+    .fetch_one(&pool)
+    .await?;
+
+    Ok(Json(member))
+}
+
+/// Stops sharing category with user. Owner removes any member, members may
+/// only leave themselves.
+pub async fn remove_member(
+    Extension(pool): Extension<SqlitePool>,
+    Path((category_id, username)): Path<(i64, String)>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<()> {
+    let access =
+        require_category_access(&pool, category_id, user_id, CategoryAccess::Viewer).await?;
+    let member_id = user_by_name(&pool, &username)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_owned()))?;
+    if access != CategoryAccess::Owner && member_id != user_id {
+        return Err(ApiError::Forbidden(
+            "Only category owner can remove other members".to_owned(),
+        ));
+    }
+
+    let removed = query!(
+        "delete from category_members where category_id = ? and user_id = ?",
+        category_id,
+        member_id
+    )
+    .execute(&pool)
+    .await?
+    .rows_affected();
+
+    if removed == 1 {
+        Ok(())
+    } else {
+        Err(ApiError::NotFound("Member not found".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{http::StatusCode, Extension};
+
+    use crate::{
+        api_data::{Categories, CategoryMemberAddRequest, WordCreateRequest},
+        auth::SessionUser,
+        category_members::{CategoryAccess, MemberRole},
+        controller::extract::{Json, Path, Query},
+        controller::{categories, words},
+        test_utils::*,
+    };
+
+    async fn add_member(
+        pool: &sqlx::SqlitePool,
+        category: i64,
+        owner: i64,
+        username: &str,
+        role: MemberRole,
+    ) {
+        let Json(member) = super::add_member(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(owner),
+            Json(CategoryMemberAddRequest {
+                username: username.to_owned(),
+                role,
+            }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(member.username, username);
+        assert_eq!(member.role, role);
+    }
+
+    fn word_request(word: &str) -> Json<WordCreateRequest> {
+        Json(WordCreateRequest {
+            word: word.to_owned(),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_viewer_and_editor() {
+        let pool = test_database_pool().await;
+        let owner = add_test_user(&pool, "owner").await;
+        let viewer = add_test_user(&pool, "viewer").await;
+        let editor = add_test_user(&pool, "editor").await;
+        let stranger = add_test_user(&pool, "stranger").await;
+        let category = add_test_category(&pool, owner).await;
+        add_test_named_word(&pool, category, "сыч").await;
+        add_member(&pool, category, owner, "viewer", MemberRole::Viewer).await;
+        add_member(&pool, category, owner, "editor", MemberRole::Editor).await;
+
+        let Json(Categories { categories }) = categories::list_categories(
+            Extension(pool.clone()),
+            SessionUser(viewer),
+            Query(Default::default()),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(categories.len(), 1);
+        assert_eq!(categories[0].access, CategoryAccess::Viewer);
+
+        let Json(listed) = words::list_words(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(viewer),
+            Query(Default::default()),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(listed.total, 1);
+
+        let error = words::create_word(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(viewer),
+            word_request("сова"),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        assert_eq!(error.status(), StatusCode::FORBIDDEN);
+
+        let error = words::create_word(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(stranger),
+            word_request("сова"),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+
+        let Json(word) = words::create_word(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(editor),
+            word_request("сова"),
+        )
+        .await
+        .expect("successful response");
+        words::delete_word(
+            Extension(pool.clone()),
+            Path((category, word.id)),
+            SessionUser(editor),
+        )
+        .await
+        .expect("successful response");
+
+        let Json(members) =
+            super::list_members(Extension(pool.clone()), Path(category), SessionUser(editor))
+                .await
+                .expect("successful response");
+        let names: Vec<_> = members
+            .members
+            .iter()
+            .map(|m| m.username.as_str())
+            .collect();
+        assert_eq!(names, vec!["editor", "viewer"]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_member() {
+        let pool = test_database_pool().await;
+        let owner = add_test_user(&pool, "owner").await;
+        let viewer = add_test_user(&pool, "viewer").await;
+        let editor = add_test_user(&pool, "editor").await;
+        let category = add_test_category(&pool, owner).await;
+        add_member(&pool, category, owner, "viewer", MemberRole::Viewer).await;
+        add_member(&pool, category, owner, "editor", MemberRole::Editor).await;
+
+        // Only owner can manage members
+        super::add_member(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(editor),
+            Json(CategoryMemberAddRequest {
+                username: "viewer".to_owned(),
+                role: MemberRole::Editor,
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        super::remove_member(
+            Extension(pool.clone()),
+            Path((category, "viewer".to_owned())),
+            SessionUser(editor),
+        )
+        .await
+        .expect_err("unsuccessful response");
+
+        // Member leaves by themselves
+        super::remove_member(
+            Extension(pool.clone()),
+            Path((category, "editor".to_owned())),
+            SessionUser(editor),
+        )
+        .await
+        .expect("successful response");
+        super::remove_member(
+            Extension(pool.clone()),
+            Path((category, "viewer".to_owned())),
+            SessionUser(owner),
+        )
+        .await
+        .expect("successful response");
+
+        let Json(Categories { categories }) = categories::list_categories(
+            Extension(pool.clone()),
+            SessionUser(viewer),
+            Query(Default::default()),
+        )
+        .await
+        .expect("successful response");
+        assert!(categories.is_empty());
+    }
+}
@@ -0,0 +1,386 @@
+use crate::api_data::{Composition, CompositionSubmitRequest, Game, Word};
+use crate::auth::SessionUser;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Path};
+use crate::controller::games::fetch_game;
+use crate::validation::{check_composition_text, validate_field};
+use axum::Extension;
+use sqlx::types::time::OffsetDateTime;
+use sqlx::{query, query_as, query_scalar, SqlitePool};
+use unicode_normalization::UnicodeNormalization;
+
+/// Looks up words with given ids among words drawn for game
+async fn game_words_by_ids(
+    exec: impl sqlx::SqliteExecutor<'_>,
+    game: &Game,
+    word_ids: &[i64],
+) -> sqlx::Result<Vec<Word>> {
+    let word_ids_json = serde_json::to_string(word_ids).expect("serialize word ids");
+    query_as!(
+        Word,
+        r#"select w.id as "id!", w.word, w.note, w.difficulty as "difficulty: u8",
+          w.tags as "tags: sqlx::types::Json<Vec<String>>", w.created_at as "created_at: OffsetDateTime"
+        from game_words gw
+        join words w on w.id = gw.word_id
+        where gw.game_id = ? and w.id in (select value from json_each(?))"#,
+        game.id,
+        word_ids_json
+    )
+    .fetch_all(exec)
+    .await
+}
+
+async fn fetch_composition(pool: &SqlitePool, game_id: i64) -> sqlx::Result<Option<Composition>> {
+    let composition = query!(
+        r#"select id as "id!", text, created_at as "created_at: OffsetDateTime",
+          updated_at as "updated_at: OffsetDateTime"
+        from compositions where game_id = ?"#,
+        game_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    match composition {
+        None => Ok(None),
+        Some(composition) => {
+            let used_words = query_as!(
+                Word,
+                r#"select w.id as "id!", w.word, w.note, w.difficulty as "difficulty: u8",
+                  w.tags as "tags: sqlx::types::Json<Vec<String>>", w.created_at as "created_at: OffsetDateTime"
+                from words w
+                join composition_words cw on cw.word_id = w.id
+                where cw.composition_id = ?
+                order by w.word"#,
+                composition.id
+            )
+            .fetch_all(pool)
+            .await?;
+
+            Ok(Some(Composition {
+                game_id,
+                text: composition.text,
+                used_words,
+                created_at: composition.created_at,
+                updated_at: composition.updated_at,
+            }))
+        }
+    }
+}
+
+/// Splits text into lowercase words: runs of letters, possibly joined with
+/// hyphens like in "иван-да-марья"
+fn word_tokens(text: &str) -> Vec<String> {
+    text.nfc()
+        .collect::<String>()
+        .to_lowercase()
+        .split(|c: char| !(c.is_alphabetic() || c == '-'))
+        .map(|token| token.trim_matches('-'))
+        .filter(|token| !token.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Checks that every used word occurs in text as whole word (or sequence of
+/// words for phrases), returning words that don't
+fn words_missing_from_text<'a>(text: &str, used_words: &'a [Word]) -> Vec<&'a str> {
+    let text_tokens = word_tokens(text);
+    used_words
+        .iter()
+        .map(|w| w.word.as_str())
+        .filter(|word| {
+            let tokens = word_tokens(word);
+            tokens.is_empty()
+                || !text_tokens
+                    .windows(tokens.len())
+                    .any(|window| window == tokens.as_slice())
+        })
+        .collect()
+}
+
+pub async fn get_composition(
+    Extension(pool): Extension<SqlitePool>,
+    Path(game_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<Json<Composition>> {
+    if fetch_game(&pool, game_id, user_id).await?.is_none() {
+        return Err(ApiError::NotFound("Game not found".to_owned()));
+    }
+
+    match fetch_composition(&pool, game_id).await? {
+        Some(composition) => Ok(Json(composition)),
+        None => Err(ApiError::NotFound("Composition not found".to_owned())),
+    }
+}
+
+/// Stores text composed in game, replacing previously submitted one
+pub async fn submit_composition(
+    Extension(pool): Extension<SqlitePool>,
+    Path(game_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+    Json(submit): Json<CompositionSubmitRequest>,
+) -> ApiResult<Json<Composition>> {
+    let game = match fetch_game(&pool, game_id, user_id).await? {
+        Some(game) => game,
+        None => return Err(ApiError::NotFound("Game not found".to_owned())),
+    };
+    if game.user_composed_id != user_id {
+        return Err(ApiError::Forbidden(
+            "Only composing user can submit composition".to_owned(),
+        ));
+    }
+    if game.finished_at.is_some() {
+        return Err(ApiError::Conflict("Game already finished".to_owned(), None));
+    }
+    validate_field("text", check_composition_text(&submit.text))?;
+
+    let mut used_word_ids = submit.used_word_ids;
+    used_word_ids.sort_unstable();
+    used_word_ids.dedup();
+
+    let mut transaction = pool.begin().await?;
+
+    // Game might have been finished after it was fetched above
+    let unfinished = query_scalar!(
+        "select id from games where id = ? and finished_at is null",
+        game_id
+    )
+    .fetch_optional(&mut transaction)
+    .await?;
+    if unfinished.is_none() {
+        return Err(ApiError::Conflict("Game already finished".to_owned(), None));
+    }
+
+    let used_words = game_words_by_ids(&mut transaction, &game, &used_word_ids).await?;
+    if used_words.len() != used_word_ids.len() {
+        return Err(ApiError::Validation(
+            "Some of used words don't belong to game".to_owned(),
+            None,
+        ));
+    }
+    let missing = words_missing_from_text(&submit.text, &used_words);
+    if !missing.is_empty() {
+        return Err(ApiError::Validation(
+            format!("Words not found in text: {}", missing.join(", ")),
+            None,
+        ));
+    }
+
+    let current_time = OffsetDateTime::now_utc();
+    let existing_id = query_scalar!("select id from compositions where game_id = ?", game_id)
+        .fetch_optional(&mut transaction)
+        .await?;
+    let composition_id = match existing_id {
+        Some(composition_id) => {
+            query!(
+                "update compositions set text = ?, updated_at = ? where id = ?",
+                submit.text,
+                current_time,
+                composition_id
+            )
+            .execute(&mut transaction)
+            .await?;
+            query!(
+                "delete from composition_words where composition_id = ?",
+                composition_id
+            )
+            .execute(&mut transaction)
+            .await?;
+            composition_id
+        }
+        None => query!(
+            "insert into compositions (game_id, text, created_at, updated_at) values (?, ?, ?, ?)",
+            game_id,
+            submit.text,
+            current_time,
+            current_time
+        )
+        .execute(&mut transaction)
+        .await?
+        .last_insert_rowid(),
+    };
+
+    for word in &used_words {
+        query!(
+            "insert into composition_words (composition_id, word_id) values (?, ?)",
+            composition_id,
+            word.id
+        )
+        .execute(&mut transaction)
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    let composition = fetch_composition(&pool, game_id)
+        .await?
+        .expect("composition just stored");
+    Ok(Json(composition))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::Extension;
+
+    use sqlx::types::{time::OffsetDateTime, Json as SqlJson};
+
+    use super::words_missing_from_text;
+    use crate::{
+        api_data::{CompositionSubmitRequest, Word},
+        auth::SessionUser,
+        controller::error::ApiError,
+        controller::extract::{Json, Path},
+        test_utils::*,
+        validation::MAX_COMPOSITION_TEXT_LENGTH,
+    };
+
+    fn word(word: &str) -> Word {
+        Word {
+            id: 0,
+            word: word.to_owned(),
+            note: None,
+            difficulty: None,
+            tags: SqlJson(vec![]),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_words_missing_from_text() {
+        let words = [
+            word("сов"),
+            word("сова"),
+            word("иван-да-марья"),
+            word("белый гриб"),
+            word("йод"),
+        ];
+        let text = "Сова советовала: «Иван-да-марья и белый\n гриб», и\u{0306}од!";
+        assert_eq!(words_missing_from_text(text, &words), vec!["сов"]);
+        assert_eq!(
+            words_missing_from_text("белый и гриб", &words[3..4]),
+            vec!["белый гриб"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_submit_composition_basic() {
+        let pool = test_database_pool().await;
+        let supplier = add_test_user(&pool, "supplier").await;
+        let composer = add_test_user(&pool, "composer").await;
+        let category = add_test_category(&pool, supplier).await;
+        let bullfinch = add_test_named_word(&pool, category, "снегирь").await;
+        let spasm = add_test_named_word(&pool, category, "спазм").await;
+        let game = add_test_game(&pool, supplier, composer).await;
+        add_test_game_word(&pool, game, bullfinch).await;
+        add_test_game_word(&pool, game, spasm).await;
+
+        let Json(composition) = super::submit_composition(
+            Extension(pool.clone()),
+            Path(game),
+            SessionUser(composer),
+            Json(CompositionSubmitRequest {
+                text: "Снегирь сидел на ветке".to_owned(),
+                used_word_ids: vec![bullfinch],
+            }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(composition.used_words.len(), 1);
+        assert_eq!(composition.used_words[0].id, bullfinch);
+
+        let Json(stored) =
+            super::get_composition(Extension(pool), Path(game), SessionUser(supplier))
+                .await
+                .expect("successful response");
+        assert_eq!(stored.text, "Снегирь сидел на ветке");
+    }
+
+    #[tokio::test]
+    async fn test_submit_composition_too_long() {
+        let pool = test_database_pool().await;
+        let supplier = add_test_user(&pool, "supplier").await;
+        let composer = add_test_user(&pool, "composer").await;
+        let game = add_test_game(&pool, supplier, composer).await;
+
+        let error = super::submit_composition(
+            Extension(pool),
+            Path(game),
+            SessionUser(composer),
+            Json(CompositionSubmitRequest {
+                text: "а".repeat(MAX_COMPOSITION_TEXT_LENGTH + 1),
+                used_word_ids: vec![],
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        let ApiError::Validation(_, Some(details)) = error else {
+            panic!("validation error with details expected");
+        };
+        assert_eq!(details["fields"][0]["field"], "text");
+        assert_eq!(details["fields"][0]["code"], "too_long");
+    }
+
+    #[tokio::test]
+    async fn test_submit_composition_word_not_in_text() {
+        let pool = test_database_pool().await;
+        let supplier = add_test_user(&pool, "supplier").await;
+        let composer = add_test_user(&pool, "composer").await;
+        let category = add_test_category(&pool, supplier).await;
+        let spasm = add_test_named_word(&pool, category, "спазм").await;
+        let game = add_test_game(&pool, supplier, composer).await;
+        add_test_game_word(&pool, game, spasm).await;
+
+        super::submit_composition(
+            Extension(pool),
+            Path(game),
+            SessionUser(composer),
+            Json(CompositionSubmitRequest {
+                text: "Снегирь сидел на ветке".to_owned(),
+                used_word_ids: vec![spasm],
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+    }
+
+    #[tokio::test]
+    async fn test_submit_composition_foreign_word() {
+        let pool = test_database_pool().await;
+        let supplier = add_test_user(&pool, "supplier").await;
+        let composer = add_test_user(&pool, "composer").await;
+        let category = add_test_category(&pool, supplier).await;
+        let foreign_word = add_test_named_word(&pool, category, "ветка").await;
+        let game = add_test_game(&pool, supplier, composer).await;
+
+        super::submit_composition(
+            Extension(pool),
+            Path(game),
+            SessionUser(composer),
+            Json(CompositionSubmitRequest {
+                text: "Снегирь сидел на ветке".to_owned(),
+                used_word_ids: vec![foreign_word],
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+    }
+
+    #[tokio::test]
+    async fn test_submit_composition_by_supplier() {
+        let pool = test_database_pool().await;
+        let supplier = add_test_user(&pool, "supplier").await;
+        let composer = add_test_user(&pool, "composer").await;
+        let game = add_test_game(&pool, supplier, composer).await;
+
+        super::submit_composition(
+            Extension(pool),
+            Path(game),
+            SessionUser(supplier),
+            Json(CompositionSubmitRequest {
+                text: "text".to_owned(),
+                used_word_ids: vec![],
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+    }
+}
@@ -0,0 +1,192 @@
+use crate::api_data::{
+    DuplicateMergeReport, DuplicateMergeRequest, DuplicateOccurrence, DuplicateWord, Duplicates,
+};
+use crate::auth::SessionUser;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::Json;
+use axum::Extension;
+use sqlx::{query, SqlitePool};
+
+/// Lists words present in more than one of user's categories
+pub async fn list_duplicates(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<Json<Duplicates>> {
+    let rows = query!(
+        r#"select w.id as "word_id!", w.word, c.id as "category_id!", c.name as category_name
+        from words w
+        join categories c on c.id = w.category_id
+        where c.user_id = ? and w.word in (
+          select w2.word from words w2
+          join categories c2 on c2.id = w2.category_id
+          where c2.user_id = ?
+          group by w2.word having count(*) > 1
+        )
+        order by w.word, c.id"#,
+        user_id,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let mut duplicates: Vec<DuplicateWord> = Vec::new();
+    for row in rows {
+        let occurrence = DuplicateOccurrence {
+            word_id: row.word_id,
+            category_id: row.category_id,
+            category_name: row.category_name,
+        };
+        match duplicates.last_mut() {
+            Some(duplicate) if duplicate.word == row.word => duplicate.occurrences.push(occurrence),
+            _ => duplicates.push(DuplicateWord {
+                word: row.word,
+                occurrences: vec![occurrence],
+            }),
+        }
+    }
+
+    Ok(Json(Duplicates { duplicates }))
+}
+
+/// Removes copies of word from user's other categories, leaving only the
+/// chosen one. Games and compositions referring to removed copies are moved
+/// to the kept word.
+pub async fn merge_duplicates(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+    Json(merge): Json<DuplicateMergeRequest>,
+) -> ApiResult<Json<DuplicateMergeReport>> {
+    let mut transaction = pool.begin().await?;
+
+    let kept = query!(
+        "select w.word from words w
+        join categories c on c.id = w.category_id
+        where w.id = ? and c.user_id = ?",
+        merge.keep_word_id,
+        user_id
+    )
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Word not found".to_owned()))?;
+
+    let removed_ids = serde_json::to_string(
+        &query!(
+            r#"select w.id as "id!" from words w
+            join categories c on c.id = w.category_id
+            where c.user_id = ? and w.word = ? and w.id != ?"#,
+            user_id,
+            kept.word,
+            merge.keep_word_id
+        )
+        .fetch_all(&mut transaction)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect::<Vec<_>>(),
+    )?;
+
+    // The same game may include several copies, then only one reference is left
+    query!(
+        "update or ignore game_words set word_id = ?
+        where word_id in (select value from json_each(?))",
+        merge.keep_word_id,
+        removed_ids
+    )
+    .execute(&mut transaction)
+    .await?;
+    query!(
+        "delete from game_words where word_id in (select value from json_each(?))",
+        removed_ids
+    )
+    .execute(&mut transaction)
+    .await?;
+    query!(
+        "update or ignore composition_words set word_id = ?
+        where word_id in (select value from json_each(?))",
+        merge.keep_word_id,
+        removed_ids
+    )
+    .execute(&mut transaction)
+    .await?;
+    query!(
+        "delete from composition_words where word_id in (select value from json_each(?))",
+        removed_ids
+    )
+    .execute(&mut transaction)
+    .await?;
+    let removed = query!(
+        "delete from words where id in (select value from json_each(?))",
+        removed_ids
+    )
+    .execute(&mut transaction)
+    .await?
+    .rows_affected();
+
+    transaction.commit().await?;
+    Ok(Json(DuplicateMergeReport {
+        kept_word_id: merge.keep_word_id,
+        removed,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::Extension;
+    use sqlx::query_scalar;
+
+    use crate::{
+        api_data::DuplicateMergeRequest, auth::SessionUser, controller::extract::Json,
+        test_utils::*,
+    };
+
+    #[tokio::test]
+    async fn test_list_and_merge_duplicates() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category1 = add_test_category(&pool, user1).await;
+        let category2 = add_test_category(&pool, user1).await;
+        let other_category = add_test_category(&pool, user2).await;
+        let kept = add_test_named_word(&pool, category1, "снегирь").await;
+        let removed = add_test_named_word(&pool, category2, "снегирь").await;
+        add_test_named_word(&pool, category1, "сипуха").await;
+        add_test_named_word(&pool, other_category, "снегирь").await;
+        let game = add_test_game(&pool, user1, user2).await;
+        add_test_game_word(&pool, game, removed).await;
+
+        let Json(duplicates) = super::list_duplicates(Extension(pool.clone()), SessionUser(user1))
+            .await
+            .expect("successful response");
+        assert_eq!(duplicates.duplicates.len(), 1);
+        assert_eq!(duplicates.duplicates[0].word, "снегирь");
+        assert_eq!(duplicates.duplicates[0].occurrences.len(), 2);
+
+        super::merge_duplicates(
+            Extension(pool.clone()),
+            SessionUser(user2),
+            Json(DuplicateMergeRequest { keep_word_id: kept }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+
+        let Json(report) = super::merge_duplicates(
+            Extension(pool.clone()),
+            SessionUser(user1),
+            Json(DuplicateMergeRequest { keep_word_id: kept }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(report.removed, 1);
+
+        let game_word = query_scalar!("select word_id from game_words where game_id = ?", game)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(game_word, kept);
+        let words_count = query_scalar!("select count(*) from words where word = 'снегирь'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(words_count, 2);
+    }
+}
@@ -0,0 +1,163 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::request_id::current_request_id;
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+/// Error returned by API handlers, rendered as
+/// `{"error": {"code": ..., "message": ..., "details": ...}}`
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String, Option<Value>),
+    UnsupportedMediaType(String),
+    /// Request is well-formed, but its data is not acceptable
+    Validation(String, Option<Value>),
+    /// Cause is logged, but not shown to client
+    Internal(anyhow::Error),
+}
+
+/// Any error not handled explicitly is internal
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(error: E) -> Self {
+        ApiError::Internal(error.into())
+    }
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(..) => StatusCode::CONFLICT,
+            ApiError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::Validation(..) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable machine-readable error identifier
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(..) => "conflict",
+            ApiError::UnsupportedMediaType(_) => "unsupported_media_type",
+            ApiError::Validation(..) => "validation_failed",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let code = self.code();
+        let (message, details) = match self {
+            ApiError::BadRequest(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::UnsupportedMediaType(message) => (message, None),
+            ApiError::Conflict(message, details) | ApiError::Validation(message, details) => {
+                (message, details)
+            }
+            ApiError::Internal(cause) => {
+                let request_id = current_request_id();
+                error!(
+                    "Internal error in request {}: {:#}",
+                    request_id.as_deref().unwrap_or("-"),
+                    cause
+                );
+                (
+                    "Internal server error".to_owned(),
+                    request_id.map(|id| json!({ "request_id": id })),
+                )
+            }
+        };
+        (
+            status,
+            Json(json!({
+                "error": {
+                    "code": code,
+                    "message": message,
+                    "details": details,
+                }
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// Unknown API routes
+pub async fn not_found() -> ApiError {
+    ApiError::NotFound("Not found".to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        response::IntoResponse,
+    };
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::ApiError;
+    use crate::{app, auth::CookieSettings, request_id::REQUEST_ID_HEADER, test_utils::*};
+
+    async fn response_json(error: ApiError) -> (StatusCode, Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_error_body() {
+        let (status, body) =
+            response_json(ApiError::NotFound("Category not found".to_owned())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(
+            body,
+            json!({"error": {"code": "not_found", "message": "Category not found", "details": null}})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_internal_error_hidden() {
+        let error: ApiError = sqlx::Error::RowNotFound.into();
+        let (status, body) = response_json(error).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"]["code"], "internal");
+        assert_eq!(body["error"]["message"], "Internal server error");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route() {
+        let pool = test_database_pool().await;
+        let response = app(pool, CookieSettings::default())
+            .oneshot(Request::get("/api/v1/unknown").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.headers().contains_key(REQUEST_ID_HEADER));
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "not_found");
+    }
+}
@@ -0,0 +1,207 @@
+//! Replacements for axum extractors which reject malformed requests with
+//! `ApiError`, so that clients get the same error body as from handlers
+
+use anyhow::anyhow;
+use axum::{
+    async_trait,
+    body::HttpBody,
+    extract::{FromRequest, FromRequestParts},
+    http::{request::Parts, Request, StatusCode},
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::error::ApiError;
+
+fn rejection_error<R: IntoResponse + std::fmt::Display>(rejection: R) -> ApiError {
+    let message = rejection.to_string();
+    match rejection.into_response().status() {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => ApiError::UnsupportedMediaType(message),
+        StatusCode::UNPROCESSABLE_ENTITY => ApiError::Validation(message, None),
+        status if status.is_client_error() => ApiError::BadRequest(message),
+        _ => ApiError::Internal(anyhow!(message)),
+    }
+}
+
+/// JSON request or response body
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::from_request(req, state)
+            .await
+            .map_err(rejection_error)?;
+        Ok(Json(value))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// URL-encoded form body
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Form<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for Form<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Form(value) = axum::Form::from_request(req, state)
+            .await
+            .map_err(rejection_error)?;
+        Ok(Form(value))
+    }
+}
+
+/// Path parameters
+#[derive(Debug)]
+pub struct Path<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) = axum::extract::Path::from_request_parts(parts, state)
+            .await
+            .map_err(rejection_error)?;
+        Ok(Path(value))
+    }
+}
+
+/// Query string parameters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) = axum::extract::Query::from_request_parts(parts, state)
+            .await
+            .map_err(rejection_error)?;
+        Ok(Query(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use crate::{
+        api_tokens::{create_token, TokenScope},
+        app,
+        auth::CookieSettings,
+        test_utils::*,
+    };
+
+    async fn error_response(request: Request<Body>) -> (StatusCode, Value) {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let token = create_token(&pool, user, "test", TokenScope::ReadWrite)
+            .await
+            .unwrap();
+        let mut request = request;
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", token.secret).parse().unwrap(),
+        );
+        let response = app(pool, CookieSettings::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_json_rejections() {
+        let (status, body) = error_response(
+            Request::post("/api/v1/words")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from("{"))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "bad_request");
+
+        let (status, body) = error_response(
+            Request::post("/api/v1/words")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"name": 1}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "validation_failed");
+
+        let (status, body) = error_response(
+            Request::post("/api/v1/words")
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(body["error"]["code"], "unsupported_media_type");
+    }
+
+    #[tokio::test]
+    async fn test_path_and_query_rejections() {
+        let (status, body) = error_response(
+            Request::get("/api/v1/words/abc")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "bad_request");
+
+        let (status, body) = error_response(
+            Request::get("/api/v1/words/1?limit=many")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "bad_request");
+    }
+}
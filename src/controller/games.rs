@@ -0,0 +1,537 @@
+use std::collections::HashMap;
+
+use crate::api_data::{Game, GameCreateRequest, Games, Word};
+use crate::auth::SessionUser;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Path};
+use crate::users::user_by_name;
+use axum::Extension;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{random, SeedableRng};
+use sqlx::types::time::OffsetDateTime;
+use sqlx::{query, query_as, query_scalar, SqlitePool};
+
+pub const DEFAULT_GAME_WORDS_COUNT: u32 = 10;
+pub const MAX_GAME_WORDS_COUNT: u32 = 100;
+
+/// Basic information about game without its words. Intermediate structure for outputting.
+struct GameBasic {
+    id: i64,
+    user_words_id: i64,
+    user_words_name: String,
+    user_composed_id: i64,
+    user_composed_name: String,
+    created_at: OffsetDateTime,
+    finished_at: Option<OffsetDateTime>,
+}
+
+fn build_game(game: GameBasic, words: Vec<Word>) -> Game {
+    Game {
+        id: game.id,
+        user_words_id: game.user_words_id,
+        user_words_name: game.user_words_name,
+        user_composed_id: game.user_composed_id,
+        user_composed_name: game.user_composed_name,
+        created_at: game.created_at,
+        finished_at: game.finished_at,
+        words,
+    }
+}
+
+/// Fetches game if user participates in it either as words supplier or as composer
+pub async fn fetch_game(
+    pool: &SqlitePool,
+    game_id: i64,
+    user_id: i64,
+) -> sqlx::Result<Option<Game>> {
+    let game = query_as!(
+        GameBasic,
+        r#"select g.id as "id!", g.user_words_id, uw.name as user_words_name,
+          g.user_composed_id, uc.name as user_composed_name,
+          g.created_at as "created_at: OffsetDateTime",
+          g.finished_at as "finished_at: OffsetDateTime"
+        from games g
+        join users uw on uw.id = g.user_words_id
+        join users uc on uc.id = g.user_composed_id
+        where g.id = ? and ? in (g.user_words_id, g.user_composed_id)"#,
+        game_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let game = match game {
+        Some(game) => game,
+        None => return Ok(None),
+    };
+    let words = query_as!(
+        Word,
+        r#"select w.id as "id!", w.word, w.note, w.difficulty as "difficulty: u8",
+          w.tags as "tags: sqlx::types::Json<Vec<String>>", w.created_at as "created_at: OffsetDateTime"
+        from game_words gw
+        join words w on w.id = gw.word_id
+        where gw.game_id = ?
+        order by gw.position"#,
+        game.id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Some(build_game(game, words)))
+}
+
+/// Picks `count` words out of candidates in order determined by `seed`.
+/// Same candidates and seed always give same result.
+fn draw_words(mut candidate_ids: Vec<i64>, count: usize, seed: i64) -> Vec<i64> {
+    candidate_ids.sort_unstable();
+    let mut rng = StdRng::seed_from_u64(seed as u64);
+    candidate_ids.shuffle(&mut rng);
+    candidate_ids.truncate(count);
+    candidate_ids
+}
+
+pub async fn list_games(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<Json<Games>> {
+    let games_basic = query_as!(
+        GameBasic,
+        r#"select g.id as "id!", g.user_words_id, uw.name as user_words_name,
+          g.user_composed_id, uc.name as user_composed_name,
+          g.created_at as "created_at: OffsetDateTime",
+          g.finished_at as "finished_at: OffsetDateTime"
+        from games g
+        join users uw on uw.id = g.user_words_id
+        join users uc on uc.id = g.user_composed_id
+        where ? in (g.user_words_id, g.user_composed_id)
+        order by g.created_at desc, g.id desc"#,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    // Words of all listed games at once
+    let word_rows = query!(
+        r#"select gw.game_id, w.id as "id!", w.word, w.note, w.difficulty as "difficulty: u8",
+          w.tags as "tags: sqlx::types::Json<Vec<String>>", w.created_at as "created_at: OffsetDateTime"
+        from game_words gw
+        join games g on g.id = gw.game_id
+        join words w on w.id = gw.word_id
+        where ? in (g.user_words_id, g.user_composed_id)
+        order by gw.game_id, gw.position"#,
+        user_id
+    )
+    .fetch_all(&pool)
+    .await?;
+    let mut words: HashMap<i64, Vec<Word>> = HashMap::new();
+    for row in word_rows {
+        words.entry(row.game_id).or_default().push(Word {
+            id: row.id,
+            word: row.word,
+            note: row.note,
+            difficulty: row.difficulty,
+            tags: row.tags,
+            created_at: row.created_at,
+        });
+    }
+
+    let games = games_basic
+        .into_iter()
+        .map(|game| {
+            let game_words = words.remove(&game.id).unwrap_or_default();
+            build_game(game, game_words)
+        })
+        .collect();
+    Ok(Json(Games { games }))
+}
+
+/// Starts new game where current user supplies words and user named in
+/// request composes. Game's words are drawn randomly from supplier's
+/// categories and stored along with the seed of the draw, so game always
+/// shows the same words.
+pub async fn create_game(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+    Json(game_create): Json<GameCreateRequest>,
+) -> ApiResult<Json<Game>> {
+    let composer_id = match user_by_name(&pool, &game_create.composer).await? {
+        Some(id) => id,
+        None => return Err(ApiError::NotFound("User not found".to_owned())),
+    };
+    if composer_id == user_id {
+        return Err(ApiError::BadRequest(
+            "Can't start game with yourself".to_owned(),
+        ));
+    }
+
+    let words_count = game_create.words_count.unwrap_or(DEFAULT_GAME_WORDS_COUNT);
+    if words_count == 0 || words_count > MAX_GAME_WORDS_COUNT {
+        return Err(ApiError::Validation(
+            format!("Words count must be between 1 and {}", MAX_GAME_WORDS_COUNT),
+            None,
+        ));
+    }
+    let mut category_ids = game_create.category_ids;
+    category_ids.sort_unstable();
+    category_ids.dedup();
+    if category_ids.is_empty() {
+        return Err(ApiError::Validation(
+            "No categories selected".to_owned(),
+            None,
+        ));
+    }
+    let category_ids_json = serde_json::to_string(&category_ids).expect("serialize category ids");
+    let exclude_recent_games = game_create.exclude_recent_games.unwrap_or(0);
+
+    let mut transaction = pool.begin().await?;
+
+    let own_categories_count = query_scalar!(
+        "select count(*) from categories
+        where user_id = ? and id in (select value from json_each(?))",
+        user_id,
+        category_ids_json
+    )
+    .fetch_one(&mut transaction)
+    .await?;
+    if own_categories_count as usize != category_ids.len() {
+        return Err(ApiError::NotFound("Category not found".to_owned()));
+    }
+
+    let candidate_ids = query_scalar!(
+        r#"select w.id as "id!" from words w
+        where w.category_id in (select value from json_each(?))
+          and w.id not in (
+            select gw.word_id from game_words gw
+            where gw.game_id in (
+              select id from games where user_words_id = ?
+              order by created_at desc, id desc limit ?
+            )
+          )"#,
+        category_ids_json,
+        user_id,
+        exclude_recent_games
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+
+    let seed = random::<i64>();
+    let word_ids = draw_words(candidate_ids, words_count as usize, seed);
+    if word_ids.is_empty() {
+        return Err(ApiError::Validation(
+            "No words available for game".to_owned(),
+            None,
+        ));
+    }
+
+    let current_time = OffsetDateTime::now_utc();
+    let game_id = query!(
+        "insert into games (user_words_id, user_composed_id, created_at, seed) values (?, ?, ?, ?)",
+        user_id,
+        composer_id,
+        current_time,
+        seed
+    )
+    .execute(&mut transaction)
+    .await?
+    .last_insert_rowid();
+
+    for (position, word_id) in word_ids.iter().enumerate() {
+        let position = position as i64;
+        query!(
+            "insert into game_words (game_id, word_id, position) values (?, ?, ?)",
+            game_id,
+            word_id,
+            position
+        )
+        .execute(&mut transaction)
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    let game = fetch_game(&pool, game_id, user_id)
+        .await?
+        .expect("game just created");
+    Ok(Json(game))
+}
+
+pub async fn get_game(
+    Extension(pool): Extension<SqlitePool>,
+    Path(game_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<Json<Game>> {
+    match fetch_game(&pool, game_id, user_id).await? {
+        Some(game) => Ok(Json(game)),
+        None => Err(ApiError::NotFound("Game not found".to_owned())),
+    }
+}
+
+pub async fn finish_game(
+    Extension(pool): Extension<SqlitePool>,
+    Path(game_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<Json<Game>> {
+    let game = match fetch_game(&pool, game_id, user_id).await? {
+        Some(game) => game,
+        None => return Err(ApiError::NotFound("Game not found".to_owned())),
+    };
+    if game.finished_at.is_some() {
+        return Err(ApiError::Conflict("Game already finished".to_owned(), None));
+    }
+
+    let current_time = OffsetDateTime::now_utc();
+    let updated = query!(
+        "update games set finished_at = ? where id = ? and finished_at is null",
+        current_time,
+        game_id
+    )
+    .execute(&pool)
+    .await?
+    .rows_affected();
+    // Concurrent request might have finished it after the check above
+    if updated != 1 {
+        return Err(ApiError::Conflict("Game already finished".to_owned(), None));
+    }
+
+    Ok(Json(Game {
+        finished_at: Some(current_time),
+        ..game
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::Extension;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use crate::{
+        api_data::GameCreateRequest,
+        auth::SessionUser,
+        controller::extract::{Json, Path},
+        db::connect_options,
+        schema::migrate,
+        test_utils::*,
+    };
+
+    fn game_create_request(composer: &str, category_ids: Vec<i64>) -> GameCreateRequest {
+        GameCreateRequest {
+            composer: composer.to_owned(),
+            category_ids,
+            words_count: Some(3),
+            exclude_recent_games: None,
+        }
+    }
+
+    #[test]
+    fn test_draw_words_reproducible() {
+        let candidates: Vec<i64> = (1..=20).collect();
+        let drawn = super::draw_words(candidates.clone(), 5, 42);
+        assert_eq!(drawn.len(), 5);
+        let mut reversed = candidates;
+        reversed.reverse();
+        assert_eq!(super::draw_words(reversed, 5, 42), drawn);
+    }
+
+    #[tokio::test]
+    async fn test_create_game_basic() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        for _ in 0..5 {
+            add_test_word(&pool, category).await;
+        }
+
+        let Json(game) = super::create_game(
+            Extension(pool.clone()),
+            SessionUser(user1),
+            Json(game_create_request("user2", vec![category])),
+        )
+        .await
+        .expect("successful response");
+
+        assert!(game.id > 0);
+        assert_eq!(game.user_words_id, user1);
+        assert_eq!(game.user_composed_id, user2);
+        assert_eq!(game.user_composed_name, "user2");
+        assert!(game.finished_at.is_none());
+        assert_eq!(game.words.len(), 3);
+
+        let Json(reloaded) = super::get_game(Extension(pool), Path(game.id), SessionUser(user2))
+            .await
+            .expect("successful response");
+        let ids = |g: &crate::api_data::Game| g.words.iter().map(|w| w.id).collect::<Vec<_>>();
+        assert_eq!(ids(&reloaded), ids(&game));
+    }
+
+    #[tokio::test]
+    async fn test_create_game_redraw_from_stored_seed() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        let mut candidates = vec![];
+        for _ in 0..10 {
+            candidates.push(add_test_word(&pool, category).await);
+        }
+
+        let Json(game) = super::create_game(
+            Extension(pool.clone()),
+            SessionUser(user1),
+            Json(game_create_request("user2", vec![category])),
+        )
+        .await
+        .expect("successful response");
+
+        let seed: Option<i64> = sqlx::query_scalar("select seed from games where id = ?")
+            .bind(game.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let word_ids: Vec<i64> = game.words.iter().map(|w| w.id).collect();
+        assert_eq!(
+            super::draw_words(candidates, 3, seed.expect("seed stored")),
+            word_ids
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_game_excludes_recent_words() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        for _ in 0..4 {
+            add_test_word(&pool, category).await;
+        }
+
+        let Json(first) = super::create_game(
+            Extension(pool.clone()),
+            SessionUser(user1),
+            Json(game_create_request("user2", vec![category])),
+        )
+        .await
+        .expect("successful response");
+
+        let Json(second) = super::create_game(
+            Extension(pool),
+            SessionUser(user1),
+            Json(GameCreateRequest {
+                exclude_recent_games: Some(1),
+                ..game_create_request("user2", vec![category])
+            }),
+        )
+        .await
+        .expect("successful response");
+
+        assert_eq!(second.words.len(), 1);
+        assert!(first.words.iter().all(|w| w.id != second.words[0].id));
+    }
+
+    #[tokio::test]
+    async fn test_create_game_other_users_category() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user2).await;
+        add_test_word(&pool, category).await;
+
+        super::create_game(
+            Extension(pool),
+            SessionUser(user1),
+            Json(game_create_request("user2", vec![category])),
+        )
+        .await
+        .expect_err("unsuccessful response");
+    }
+
+    #[tokio::test]
+    async fn test_create_game_unknown_composer() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+
+        super::create_game(
+            Extension(pool),
+            SessionUser(user),
+            Json(game_create_request("nobody", vec![])),
+        )
+        .await
+        .expect_err("unsuccessful response");
+    }
+
+    #[tokio::test]
+    async fn test_list_games_basic() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let user3 = add_test_user(&pool, "user3").await;
+        add_test_game(&pool, user1, user2).await;
+        add_test_game(&pool, user2, user1).await;
+        add_test_game(&pool, user2, user3).await;
+
+        let Json(games) = super::list_games(Extension(pool), SessionUser(user1))
+            .await
+            .expect("successful response");
+
+        assert_eq!(games.games.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_games_single_connection() {
+        let options = connect_options(":memory:").unwrap();
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        migrate(&pool).await.unwrap();
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        let word1 = add_test_named_word(&pool, category, "сова").await;
+        let word2 = add_test_named_word(&pool, category, "сыч").await;
+        let game1 = add_test_game(&pool, user1, user2).await;
+        add_test_game_word(&pool, game1, word2).await;
+        add_test_game_word(&pool, game1, word1).await;
+        add_test_game(&pool, user2, user1).await;
+
+        let Json(games) = super::list_games(Extension(pool), SessionUser(user1))
+            .await
+            .expect("successful response");
+        assert_eq!(games.games.len(), 2);
+        let game = games.games.iter().find(|g| g.id == game1).unwrap();
+        let word_ids: Vec<i64> = game.words.iter().map(|w| w.id).collect();
+        assert_eq!(word_ids, vec![word2, word1]);
+    }
+
+    #[tokio::test]
+    async fn test_get_game_another_user() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let user3 = add_test_user(&pool, "user3").await;
+        let game = add_test_game(&pool, user1, user2).await;
+
+        super::get_game(Extension(pool), Path(game), SessionUser(user3))
+            .await
+            .expect_err("unsuccessful response");
+    }
+
+    #[tokio::test]
+    async fn test_finish_game_basic() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let game = add_test_game(&pool, user1, user2).await;
+
+        let Json(finished) =
+            super::finish_game(Extension(pool.clone()), Path(game), SessionUser(user2))
+                .await
+                .expect("successful response");
+        assert!(finished.finished_at.is_some());
+
+        super::finish_game(Extension(pool), Path(game), SessionUser(user1))
+            .await
+            .expect_err("game can't be finished twice");
+    }
+}
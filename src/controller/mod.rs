@@ -1,4 +1,14 @@
+pub mod api_tokens;
+pub mod archive;
 pub mod auth;
 pub mod categories;
-pub mod utils;
+pub mod category_members;
+pub mod compositions;
+pub mod duplicates;
+pub mod error;
+pub mod extract;
+pub mod games;
+pub mod search;
+pub mod sessions;
+pub mod share_links;
 pub mod words;
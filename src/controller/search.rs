@@ -0,0 +1,159 @@
+use crate::api_data::{SearchCategory, SearchResults, Word};
+use crate::auth::SessionUser;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Query};
+use axum::Extension;
+use serde::Deserialize;
+use sqlx::types::time::OffsetDateTime;
+use sqlx::{query, SqlitePool};
+
+pub const MAX_SEARCH_RESULTS: i64 = 100;
+
+/// Shortest term trigram index can look up
+pub const MIN_SEARCH_TERM_LENGTH: usize = 3;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    q: String,
+}
+
+/// Builds FTS5 query matching words that contain all terms of `q`, ignoring
+/// terms too short for lookup. Terms are quoted so that user input isn't
+/// interpreted as query syntax.
+fn fts_query(q: &str) -> Option<String> {
+    let terms: Vec<String> = q
+        .to_lowercase()
+        .replace('ё', "е")
+        .split_whitespace()
+        .filter(|term| term.chars().count() >= MIN_SEARCH_TERM_LENGTH)
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Finds words and notes containing query across all user's categories
+pub async fn search(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+    Query(search_query): Query<SearchQuery>,
+) -> ApiResult<Json<SearchResults>> {
+    let fts_query = fts_query(&search_query.q).ok_or_else(|| {
+        ApiError::Validation(
+            format!(
+                "Search query must have term of at least {} characters",
+                MIN_SEARCH_TERM_LENGTH
+            ),
+            None,
+        )
+    })?;
+
+    let rows = query!(
+        r#"select w.id as "id!", w.word, w.note, w.difficulty as "difficulty: u8",
+          w.tags as "tags: sqlx::types::Json<Vec<String>>", w.created_at as "created_at: OffsetDateTime",
+          c.id as "category_id!", c.name as category_name
+        from words_search s
+        join words w on w.id = s.rowid
+        join categories c on c.id = w.category_id
+        where words_search match ? and c.user_id = ?
+        order by s.rank
+        limit ?"#,
+        fts_query,
+        user_id,
+        MAX_SEARCH_RESULTS
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let mut categories: Vec<SearchCategory> = Vec::new();
+    for row in rows {
+        let word = Word {
+            id: row.id,
+            word: row.word,
+            note: row.note,
+            difficulty: row.difficulty,
+            tags: row.tags,
+            created_at: row.created_at,
+        };
+        match categories
+            .iter_mut()
+            .find(|category| category.category_id == row.category_id)
+        {
+            Some(category) => category.words.push(word),
+            None => categories.push(SearchCategory {
+                category_id: row.category_id,
+                category_name: row.category_name,
+                words: vec![word],
+            }),
+        }
+    }
+
+    Ok(Json(SearchResults { categories }))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::Extension;
+
+    use super::{fts_query, SearchQuery};
+    use crate::{
+        auth::SessionUser,
+        controller::extract::{Json, Query},
+        test_utils::*,
+    };
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("Ёлка"), Some("\"елка\"".to_owned()));
+        assert_eq!(fts_query("по снег\""), Some("\"снег\"\"\"".to_owned()));
+        assert_eq!(fts_query(" ab "), None);
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let other_user = add_test_user(&pool, "other").await;
+        let birds = add_test_category(&pool, user).await;
+        let weather = add_test_category(&pool, user).await;
+        let foreign = add_test_category(&pool, other_user).await;
+        let bullfinch = add_test_named_word(&pool, birds, "снегирь").await;
+        add_test_named_word(&pool, birds, "сова").await;
+        let snow = add_test_named_word(&pool, weather, "снег").await;
+        add_test_named_word(&pool, foreign, "снеговик").await;
+
+        let Json(results) = super::search(
+            Extension(pool.clone()),
+            SessionUser(user),
+            Query(SearchQuery {
+                q: "Снег".to_owned(),
+            }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(results.categories.len(), 2);
+        let mut found: Vec<_> = results
+            .categories
+            .iter()
+            .flat_map(|c| c.words.iter().map(move |w| (c.category_id, w.id)))
+            .collect();
+        found.sort_unstable();
+        assert_eq!(found, vec![(birds, bullfinch), (weather, snow)]);
+
+        // Index follows word changes
+        sqlx::query!("update words set word = 'сыч' where id = ?", bullfinch)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let Json(results) = super::search(
+            Extension(pool),
+            SessionUser(user),
+            Query(SearchQuery {
+                q: "снег".to_owned(),
+            }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(results.categories.len(), 1);
+        assert_eq!(results.categories[0].category_id, weather);
+    }
+}
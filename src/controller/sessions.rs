@@ -0,0 +1,104 @@
+use crate::api_data::Sessions;
+use crate::auth::CurrentSession;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Path};
+use crate::users;
+use axum::Extension;
+use sqlx::SqlitePool;
+
+pub async fn list_sessions(
+    Extension(pool): Extension<SqlitePool>,
+    session: CurrentSession,
+) -> ApiResult<Json<Sessions>> {
+    let sessions = users::list_sessions(&pool, session.user_id, session.id).await?;
+    Ok(Json(Sessions { sessions }))
+}
+
+/// Revokes one of current user's sessions, which may be current session
+pub async fn delete_session(
+    Extension(pool): Extension<SqlitePool>,
+    Path(session_id): Path<i64>,
+    session: CurrentSession,
+) -> ApiResult<()> {
+    if users::delete_session(&pool, session.user_id, session_id).await? {
+        Ok(())
+    } else {
+        Err(ApiError::NotFound("Session not found".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::Extension;
+    use sqlx::query_scalar;
+
+    use crate::{
+        auth::CurrentSession,
+        controller::extract::{Json, Path},
+        test_utils::*,
+        users::{create_session, get_session_user},
+    };
+
+    #[tokio::test]
+    async fn test_list_sessions_basic() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let secret = create_session(&pool, user1, "browser 1").await.unwrap();
+        create_session(&pool, user1, "browser 2").await.unwrap();
+        create_session(&pool, user2, "browser 3").await.unwrap();
+        let session = get_session_user(&pool, &secret).await.unwrap().unwrap();
+
+        let Json(sessions) = super::list_sessions(
+            Extension(pool),
+            CurrentSession {
+                id: session.id,
+                user_id: user1,
+            },
+        )
+        .await
+        .expect("successful response");
+
+        assert_eq!(sessions.sessions.len(), 2);
+        let current = sessions.sessions.iter().find(|s| s.current).unwrap();
+        assert_eq!(current.created_user_agent, "browser 1");
+    }
+
+    #[tokio::test]
+    async fn test_delete_session_another_user() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let secret1 = create_session(&pool, user1, "browser 1").await.unwrap();
+        let session1 = get_session_user(&pool, &secret1).await.unwrap().unwrap();
+        let secret2 = create_session(&pool, user2, "browser 2").await.unwrap();
+        let session2 = get_session_user(&pool, &secret2).await.unwrap().unwrap();
+
+        super::delete_session(
+            Extension(pool.clone()),
+            Path(session1.id),
+            CurrentSession {
+                id: session2.id,
+                user_id: user2,
+            },
+        )
+        .await
+        .expect_err("unsuccessful response");
+        super::delete_session(
+            Extension(pool.clone()),
+            Path(session1.id),
+            CurrentSession {
+                id: session1.id,
+                user_id: user1,
+            },
+        )
+        .await
+        .expect("successful response");
+
+        let sessions_count = query_scalar!("select count(*) from sessions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(sessions_count, 1);
+    }
+}
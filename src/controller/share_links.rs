@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use crate::api_data::{CreatedShareLink, ShareLinkCreateRequest, ShareLinks, SharedCategory, Word};
+use crate::auth::SessionUser;
+use crate::category_members::CategoryAccess;
+use crate::controller::category_members::require_category_access;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Path, Query};
+use crate::db::russian_cmp;
+use crate::share_links;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Extension;
+use handlebars::Handlebars;
+use serde::Deserialize;
+use sqlx::types::time::OffsetDateTime;
+use sqlx::{query_as, query_scalar, SqlitePool};
+
+// Only owner manages links, since they expose category to anyone
+
+pub async fn list_share_links(
+    Extension(pool): Extension<SqlitePool>,
+    Path(category_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<Json<ShareLinks>> {
+    require_category_access(&pool, category_id, user_id, CategoryAccess::Owner).await?;
+    let links = share_links::list_share_links(&pool, category_id).await?;
+    Ok(Json(ShareLinks { links }))
+}
+
+pub async fn create_share_link(
+    Extension(pool): Extension<SqlitePool>,
+    Path(category_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+    Json(request): Json<ShareLinkCreateRequest>,
+) -> ApiResult<Json<CreatedShareLink>> {
+    require_category_access(&pool, category_id, user_id, CategoryAccess::Owner).await?;
+    if request
+        .expires_at
+        .is_some_and(|t| t <= OffsetDateTime::now_utc())
+    {
+        return Err(ApiError::Validation(
+            "Expiry time is in the past".to_owned(),
+            None,
+        ));
+    }
+    let link = share_links::create_share_link(&pool, category_id, request.expires_at).await?;
+    Ok(Json(link))
+}
+
+pub async fn revoke_share_link(
+    Extension(pool): Extension<SqlitePool>,
+    Path((category_id, link_id)): Path<(i64, i64)>,
+    SessionUser(user_id): SessionUser,
+) -> ApiResult<()> {
+    require_category_access(&pool, category_id, user_id, CategoryAccess::Owner).await?;
+    if share_links::revoke_share_link(&pool, category_id, link_id).await? {
+        Ok(())
+    } else {
+        Err(ApiError::NotFound("Share link not found".to_owned()))
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SharedFormat {
+    #[default]
+    Html,
+    Json,
+}
+
+#[derive(Deserialize)]
+pub struct SharedCategoryQuery {
+    #[serde(default)]
+    format: SharedFormat,
+}
+
+/// Public page with words of category shared by link, in alphabetical order.
+/// Available to anyone with link, without logging in.
+pub async fn shared_category(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(handlebars): Extension<Arc<Handlebars<'_>>>,
+    Path(token): Path<String>,
+    Query(shared_query): Query<SharedCategoryQuery>,
+) -> ApiResult<Response> {
+    let category_id = share_links::shared_category_id(&pool, &token)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Share link not found".to_owned()))?;
+
+    // Category might have been deleted after link was looked up
+    let name = query_scalar!("select name from categories where id = ?", category_id)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Share link not found".to_owned()))?;
+    let mut words = query_as!(
+        Word,
+        r#"select id as "id!", word, note, difficulty as "difficulty: u8",
+          tags as "tags: sqlx::types::Json<Vec<String>>", created_at as "created_at: OffsetDateTime"
+        from words where category_id = ?"#,
+        category_id
+    )
+    .fetch_all(&pool)
+    .await?;
+    words.sort_by(|a, b| russian_cmp(&a.word, &b.word));
+
+    let shared = SharedCategory { name, words };
+    Ok(match shared_query.format {
+        SharedFormat::Json => Json(shared).into_response(),
+        SharedFormat::Html => {
+            Html(handlebars.render("shared_category.hbs", &shared)?).into_response()
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        Extension,
+    };
+    use tower::ServiceExt;
+
+    use crate::{
+        api_data::ShareLinkCreateRequest,
+        app,
+        auth::CookieSettings,
+        auth::SessionUser,
+        controller::extract::{Json, Path},
+        test_utils::*,
+    };
+
+    #[tokio::test]
+    async fn test_shared_category_page() {
+        let pool = test_database_pool().await;
+        let owner = add_test_user(&pool, "owner").await;
+        let other = add_test_user(&pool, "other").await;
+        let category = add_test_category(&pool, owner).await;
+        add_test_named_word(&pool, category, "<сыч>").await;
+
+        super::create_share_link(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(other),
+            Json(ShareLinkCreateRequest::default()),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        let Json(created) = super::create_share_link(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(owner),
+            Json(ShareLinkCreateRequest::default()),
+        )
+        .await
+        .expect("successful response");
+        let app = app(pool.clone(), CookieSettings::default());
+
+        let get = |uri: String| Request::get(uri).body(Body::empty()).unwrap();
+        let response = app
+            .clone()
+            .oneshot(get(created.path.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        assert!(page.contains("&lt;сыч&gt;"));
+
+        let response = app
+            .clone()
+            .oneshot(get(format!("{}?format=json", created.path)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let shared: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(shared["words"][0]["word"], "<сыч>");
+
+        super::revoke_share_link(
+            Extension(pool.clone()),
+            Path((category, created.link.id)),
+            SessionUser(owner),
+        )
+        .await
+        .expect("successful response");
+        let response = app.oneshot(get(created.path)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
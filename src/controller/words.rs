@@ -1,31 +1,313 @@
-use crate::api_data::{Word, WordCreateRequest, Words};
+use crate::api_data::{
+    Word, WordCreateRequest, WordImportReport, WordUpdateRequest, Words, WordsBulkAction,
+    WordsBulkReport, WordsBulkRequest,
+};
 use crate::auth::SessionUser;
-use crate::controller::utils::InternalServerErrorResultExt;
-use axum::extract::Path;
-use axum::http::StatusCode;
-use axum::response::Result;
-use axum::{Extension, Json};
-use sqlx::types::time::OffsetDateTime;
-use sqlx::{query, query_as, query_scalar, SqlitePool};
+use crate::category_members::CategoryAccess;
+use crate::controller::category_members::require_category_access;
+use crate::controller::error::{ApiError, ApiResult};
+use crate::controller::extract::{Json, Path, Query};
+use crate::db::is_unique_violation;
+use crate::validation::{
+    check_difficulty, normalize_note, normalize_tags, normalize_word, FieldErrors,
+};
+use crate::word_lists::{self, WordListError, WordListFormat};
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::types::{time::OffsetDateTime, Json as SqlJson};
+use sqlx::{query, query_as, query_scalar, QueryBuilder, Sqlite, SqlitePool};
 
+pub const DEFAULT_WORDS_PAGE_SIZE: u32 = 50;
+pub const MAX_WORDS_PAGE_SIZE: u32 = 200;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WordSort {
+    /// In Russian alphabetical order
+    Alphabetical,
+    #[default]
+    Newest,
+    Oldest,
+}
+
+impl WordSort {
+    /// Column words are sorted by, besides id
+    fn key(self) -> &'static str {
+        match self {
+            WordSort::Alphabetical => "w.word collate russian",
+            WordSort::Newest | WordSort::Oldest => "w.created_at",
+        }
+    }
+
+    fn descending(self) -> bool {
+        self == WordSort::Newest
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListWordsQuery {
+    #[serde(default)]
+    sort: WordSort,
+    /// Substring words must contain
+    q: Option<String>,
+    cursor: Option<String>,
+    limit: Option<u32>,
+}
+
+/// Position after last word of page: its sort key and id, along with sort
+/// the key belongs to. Passed to clients as opaque string.
+#[derive(Serialize, Deserialize)]
+struct WordsCursor {
+    sort: WordSort,
+    key: String,
+    id: i64,
+}
+
+impl WordsCursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("serialize cursor"))
+    }
+
+    fn decode(cursor: &str) -> Option<WordsCursor> {
+        let data = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct WordRow {
+    #[sqlx(flatten)]
+    word: Word,
+    sort_key: String,
+}
+
+/// LIKE pattern matching words that contain `q`
+fn word_search_pattern(q: &str) -> Option<String> {
+    let q = q.trim().to_lowercase();
+    if q.is_empty() {
+        return None;
+    }
+    let escaped = q
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    Some(format!("%{}%", escaped))
+}
+
+fn push_words_filter(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    category_id: i64,
+    pattern: &Option<String>,
+) {
+    builder
+        .push(" from words w where w.category_id = ")
+        .push_bind(category_id);
+    if let Some(pattern) = pattern {
+        builder
+            .push(" and w.word like ")
+            .push_bind(pattern.clone())
+            .push(r" escape '\'");
+    }
+}
+
+/// Lists category's words page by page, optionally filtered by substring
 pub async fn list_words(
     Extension(pool): Extension<SqlitePool>,
     Path(category_id): Path<i64>,
     SessionUser(user_id): SessionUser,
-) -> Result<Json<Words>> {
-    let words = query_as!(
+    Query(list_query): Query<ListWordsQuery>,
+) -> ApiResult<Json<Words>> {
+    require_category_access(&pool, category_id, user_id, CategoryAccess::Viewer).await?;
+
+    let sort = list_query.sort;
+    let limit = list_query
+        .limit
+        .unwrap_or(DEFAULT_WORDS_PAGE_SIZE)
+        .clamp(1, MAX_WORDS_PAGE_SIZE);
+    let cursor = match list_query.cursor {
+        Some(cursor) => match WordsCursor::decode(&cursor) {
+            Some(cursor) if cursor.sort == sort => Some(cursor),
+            Some(_) => {
+                return Err(ApiError::BadRequest(
+                    "Cursor belongs to different sort".to_owned(),
+                ))
+            }
+            None => return Err(ApiError::BadRequest("Invalid cursor".to_owned())),
+        },
+        None => None,
+    };
+    let pattern = list_query.q.as_deref().and_then(word_search_pattern);
+
+    let mut count_builder = QueryBuilder::new("select count(*)");
+    push_words_filter(&mut count_builder, category_id, &pattern);
+    let (total,): (i64,) = count_builder.build_query_as().fetch_one(&pool).await?;
+
+    let mut builder = QueryBuilder::new(format!(
+        "select w.id, w.word, w.note, w.difficulty, w.tags, w.created_at, {} as sort_key",
+        sort.key()
+    ));
+    push_words_filter(&mut builder, category_id, &pattern);
+    let (direction, after) = if sort.descending() {
+        ("desc", "<")
+    } else {
+        ("asc", ">")
+    };
+    if let Some(cursor) = cursor {
+        builder
+            .push(format!(" and ({} {} ", sort.key(), after))
+            .push_bind(cursor.key.clone())
+            .push(format!(" or ({} = ", sort.key()))
+            .push_bind(cursor.key)
+            .push(format!(" and w.id {} ", after))
+            .push_bind(cursor.id)
+            .push("))");
+    }
+    builder
+        .push(format!(
+            " order by {} {}, w.id {} limit ",
+            sort.key(),
+            direction,
+            direction
+        ))
+        // One more word tells whether there is next page
+        .push_bind(limit + 1);
+    let mut rows: Vec<WordRow> = builder.build_query_as().fetch_all(&pool).await?;
+
+    let next_cursor = if rows.len() > limit as usize {
+        rows.truncate(limit as usize);
+        rows.last().map(|row| {
+            WordsCursor {
+                sort,
+                key: row.sort_key.clone(),
+                id: row.word.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(Words {
+        words: rows.into_iter().map(|row| row.word).collect(),
+        next_cursor,
+        total,
+    }))
+}
+
+/// Validated word fields, `None` where field is left unchanged
+struct WordChanges {
+    word: Option<String>,
+    note: Option<Option<String>>,
+    difficulty: Option<Option<u8>>,
+    tags: Option<Vec<String>>,
+}
+
+fn validate_word_changes(update: WordUpdateRequest) -> ApiResult<WordChanges> {
+    let mut errors = FieldErrors::new();
+    let changes = WordChanges {
+        word: update
+            .word
+            .and_then(|word| errors.check("word", normalize_word(&word))),
+        note: update.note.map(|note| {
+            note.and_then(|note| errors.check("note", normalize_note(&note)).flatten())
+        }),
+        difficulty: update.difficulty.map(|difficulty| {
+            difficulty
+                .and_then(|difficulty| errors.check("difficulty", check_difficulty(difficulty)))
+        }),
+        tags: update.tags.map(|tags| {
+            errors
+                .check("tags", normalize_tags(&tags))
+                .unwrap_or_default()
+        }),
+    };
+    errors.into_result()?;
+    Ok(changes)
+}
+
+async fn fetch_word(exec: impl sqlx::SqliteExecutor<'_>, word_id: i64) -> sqlx::Result<Word> {
+    query_as!(
         Word,
-        r#"select w.id as "id!", w.word from words w
-        join categories c on w.category_id = c.id
-        where w.category_id = ? and c.user_id = ?"#,
-        category_id,
-        user_id
+        r#"select id as "id!", word, note, difficulty as "difficulty: u8",
+          tags as "tags: sqlx::types::Json<Vec<String>>", created_at as "created_at: OffsetDateTime"
+        from words where id = ?"#,
+        word_id
     )
-    .fetch_all(&pool)
+    .fetch_one(exec)
     .await
-    .into_500()?;
+}
 
-    Ok(Json(Words { words }))
+/// Fails with conflict if category already has the word
+async fn check_word_unique(
+    exec: impl sqlx::SqliteExecutor<'_>,
+    category_id: i64,
+    word: &str,
+) -> ApiResult<()> {
+    match query_scalar!(
+        "select id from words where category_id = ? and word = ?",
+        category_id,
+        word
+    )
+    .fetch_optional(exec)
+    .await?
+    {
+        Some(existing_id) => Err(ApiError::Conflict(
+            "Word already exists in category".to_owned(),
+            Some(json!({ "existing_word_id": existing_id })),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Fails with conflict if any of words is drawn in game or used in
+/// composition. Such words are kept so that games always show the same words.
+pub async fn check_words_unused(
+    exec: impl sqlx::SqliteExecutor<'_>,
+    word_ids_json: &str,
+) -> ApiResult<()> {
+    let game_ids = query_scalar!(
+        r#"select game_id as "game_id!" from game_words
+          where word_id in (select value from json_each(?))
+        union
+        select c.game_id from composition_words cw
+          join compositions c on c.id = cw.composition_id
+          where cw.word_id in (select value from json_each(?))
+        order by 1"#,
+        word_ids_json,
+        word_ids_json
+    )
+    .fetch_all(exec)
+    .await?;
+    if game_ids.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Conflict(
+            "Words are used in games".to_owned(),
+            Some(json!({ "game_ids": game_ids })),
+        ))
+    }
+}
+
+/// Maps unique index violation, which happens when concurrent request adds
+/// the same word after `check_word_unique`, to the same conflict
+async fn write_word_error(
+    exec: impl sqlx::SqliteExecutor<'_>,
+    category_id: i64,
+    word: &str,
+    error: sqlx::Error,
+) -> ApiError {
+    if !is_unique_violation(&error) {
+        return error.into();
+    }
+    match check_word_unique(exec, category_id, word).await {
+        Err(conflict) => conflict,
+        Ok(()) => error.into(),
+    }
 }
 
 pub async fn create_word(
@@ -33,71 +315,345 @@ pub async fn create_word(
     Path(category_id): Path<i64>,
     SessionUser(user_id): SessionUser,
     Json(word_create): Json<WordCreateRequest>,
-) -> Result<Json<Word>> {
-    if let Some(_) = query_scalar!("select id from categories where user_id = ?", user_id)
-        .fetch_optional(&pool)
-        .await
-        .into_500()?
+) -> ApiResult<Json<Word>> {
+    let changes = validate_word_changes(WordUpdateRequest {
+        word: Some(word_create.word),
+        note: Some(word_create.note),
+        difficulty: Some(word_create.difficulty),
+        tags: Some(word_create.tags),
+    })?;
+    let word = changes.word.expect("word is validated");
+
+    require_category_access(&pool, category_id, user_id, CategoryAccess::Editor).await?;
+    check_word_unique(&pool, category_id, &word).await?;
+
+    let current_time = OffsetDateTime::now_utc();
+    let note = changes.note.flatten();
+    let difficulty = changes.difficulty.flatten();
+    let tags = SqlJson(changes.tags.unwrap_or_default());
+    let result = query!(
+        "insert into words (category_id, word, note, difficulty, tags, created_at, updated_at)
+        values (?, ?, ?, ?, ?, ?, ?)",
+        category_id,
+        word,
+        note,
+        difficulty,
+        tags,
+        current_time,
+        current_time
+    )
+    .execute(&pool)
+    .await;
+    let word_id = match result {
+        Ok(result) => result.last_insert_rowid(),
+        Err(error) => return Err(write_word_error(&pool, category_id, &word, error).await),
+    };
+    Ok(Json(fetch_word(&pool, word_id).await?))
+}
+
+/// Changes given fields of word, keeping its id and creation time
+pub async fn update_word(
+    Extension(pool): Extension<SqlitePool>,
+    Path((category_id, word_id)): Path<(i64, i64)>,
+    SessionUser(user_id): SessionUser,
+    Json(word_update): Json<WordUpdateRequest>,
+) -> ApiResult<Json<Word>> {
+    let changes = validate_word_changes(word_update)?;
+
+    let mut transaction = pool.begin().await?;
+    require_category_access(
+        &mut transaction,
+        category_id,
+        user_id,
+        CategoryAccess::Editor,
+    )
+    .await?;
+    let current = query_as!(
+        Word,
+        r#"select w.id as "id!", w.word, w.note, w.difficulty as "difficulty: u8",
+          w.tags as "tags: sqlx::types::Json<Vec<String>>", w.created_at as "created_at: OffsetDateTime"
+        from words w
+        where w.id = ? and w.category_id = ?"#,
+        word_id,
+        category_id
+    )
+    .fetch_optional(&mut transaction)
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Word not found".to_owned()))?;
+
+    let word = changes.word.unwrap_or(current.word.clone());
+    if word != current.word {
+        check_word_unique(&mut transaction, category_id, &word).await?;
+    }
+    let note = changes.note.unwrap_or(current.note);
+    let difficulty = changes.difficulty.unwrap_or(current.difficulty);
+    let tags = changes.tags.map(SqlJson).unwrap_or(current.tags);
+    let current_time = OffsetDateTime::now_utc();
+    if let Err(error) = query!(
+        "update words set word = ?, note = ?, difficulty = ?, tags = ?, updated_at = ?
+        where id = ?",
+        word,
+        note,
+        difficulty,
+        tags,
+        current_time,
+        word_id
+    )
+    .execute(&mut transaction)
+    .await
     {
-        let current_time = OffsetDateTime::now_utc();
-        let word_id = query!(
-            "insert into words (category_id, word, created_at, updated_at) values (?, ?, ?, ?)",
-            category_id,
-            word_create.word,
-            current_time,
-            current_time
-        )
-        .execute(&pool)
-        .await
-        .into_500()?
-        .last_insert_rowid();
-        Ok(Json(Word {
-            id: word_id,
-            word: word_create.word.to_owned(),
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, "Category not found").into())
+        return Err(write_word_error(&mut transaction, category_id, &word, error).await);
     }
+
+    let word = fetch_word(&mut transaction, word_id).await?;
+    transaction.commit().await?;
+    Ok(Json(word))
 }
 
 pub async fn delete_word(
     Extension(pool): Extension<SqlitePool>,
-    Path(category_id): Path<i64>,
-    Path(word_id): Path<i64>,
+    Path((category_id, word_id)): Path<(i64, i64)>,
     SessionUser(user_id): SessionUser,
-) -> Result<()> {
+) -> ApiResult<()> {
+    require_category_access(&pool, category_id, user_id, CategoryAccess::Editor).await?;
+    let mut transaction = pool.begin().await?;
+    check_words_unused(&mut transaction, &format!("[{}]", word_id)).await?;
     let affected_rows = query!(
-        "with deletable_words as (
-          select words.id from words
-          join categories on categories.id = words.category_id
-          where categories.user_id = ?
-        )
-        delete from words where id = ?
-          and exists (select * from deletable_words where deletable_words.id = id)
-          and category_id = ?",
-        user_id,
+        "delete from words where id = ? and category_id = ?",
         word_id,
         category_id
     )
-    .execute(&pool)
-    .await
-    .into_500()?
+    .execute(&mut transaction)
+    .await?
     .rows_affected();
 
     if affected_rows == 1 {
+        transaction.commit().await?;
         Ok(())
     } else if affected_rows == 0 {
-        Err((StatusCode::NOT_FOUND, "Not found").into())
+        Err(ApiError::NotFound("Not found".to_owned()))
     } else {
         panic!("More than one word deleted, shouldn't happen");
     }
 }
 
+/// Moves, copies or deletes several words in single transaction. All words
+/// and target category must belong to current user.
+pub async fn bulk_words(
+    Extension(pool): Extension<SqlitePool>,
+    SessionUser(user_id): SessionUser,
+    Json(bulk): Json<WordsBulkRequest>,
+) -> ApiResult<Json<WordsBulkReport>> {
+    let mut word_ids = bulk.word_ids;
+    word_ids.sort_unstable();
+    word_ids.dedup();
+    let word_ids_json = serde_json::to_string(&word_ids)?;
+
+    let mut transaction = pool.begin().await?;
+
+    let words = query!(
+        r#"select w.id as "id!", w.word from words w
+        join categories c on c.id = w.category_id
+        where c.user_id = ? and w.id in (select value from json_each(?))
+        order by w.id"#,
+        user_id,
+        word_ids_json
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+    if words.len() != word_ids.len() {
+        return Err(ApiError::NotFound("Some of words not found".to_owned()));
+    }
+
+    let mut report = WordsBulkReport {
+        affected: 0,
+        skipped_word_ids: vec![],
+    };
+    let (target_category_id, copy) = match bulk.action {
+        WordsBulkAction::Move { target_category_id } => (target_category_id, false),
+        WordsBulkAction::Copy { target_category_id } => (target_category_id, true),
+        WordsBulkAction::Delete => {
+            check_words_unused(&mut transaction, &word_ids_json).await?;
+            report.affected = query!(
+                "delete from words where id in (select value from json_each(?))",
+                word_ids_json
+            )
+            .execute(&mut transaction)
+            .await?
+            .rows_affected();
+            transaction.commit().await?;
+            return Ok(Json(report));
+        }
+    };
+
+    if query_scalar!(
+        "select id from categories where id = ? and user_id = ?",
+        target_category_id,
+        user_id
+    )
+    .fetch_optional(&mut transaction)
+    .await?
+    .is_none()
+    {
+        return Err(ApiError::NotFound("Target category not found".to_owned()));
+    }
+
+    let current_time = OffsetDateTime::now_utc();
+    for word in words {
+        if query_scalar!(
+            "select id from words where category_id = ? and word = ?",
+            target_category_id,
+            word.word
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .is_some()
+        {
+            report.skipped_word_ids.push(word.id);
+            continue;
+        }
+
+        let result = if copy {
+            query!(
+                "insert into words (category_id, word, note, difficulty, tags, created_at, updated_at)
+                select ?, word, note, difficulty, tags, ?, ? from words where id = ?",
+                target_category_id,
+                current_time,
+                current_time,
+                word.id
+            )
+            .execute(&mut transaction)
+            .await?
+        } else {
+            query!(
+                "update words set category_id = ?, updated_at = ? where id = ?",
+                target_category_id,
+                current_time,
+                word.id
+            )
+            .execute(&mut transaction)
+            .await?
+        };
+        report.affected += result.rows_affected();
+    }
+
+    transaction.commit().await?;
+    Ok(Json(report))
+}
+
+/// Adds words from word list in request body. Format is determined by
+/// `Content-Type`: `text/plain`, `text/csv` or `application/json`.
+pub async fn import_words(
+    Extension(pool): Extension<SqlitePool>,
+    Path(category_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+    headers: HeaderMap,
+    body: String,
+) -> ApiResult<Json<WordImportReport>> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("text/plain");
+    let format = match WordListFormat::from_content_type(content_type) {
+        Some(format) => format,
+        None => {
+            return Err(ApiError::UnsupportedMediaType(
+                "Word list must be text/plain, text/csv or application/json".to_owned(),
+            ))
+        }
+    };
+
+    if query_scalar!(
+        "select id from categories where id = ? and user_id = ?",
+        category_id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await?
+    .is_none()
+    {
+        return Err(ApiError::NotFound("Category not found".to_owned()));
+    }
+
+    let entries = match word_lists::parse_word_list(format, &body) {
+        Ok(entries) => entries,
+        Err(e) => return Err(ApiError::BadRequest(e.to_string())),
+    };
+    match word_lists::import_words(&pool, category_id, entries).await {
+        Ok(report) => Ok(Json(report)),
+        Err(WordListError::SqlError(e)) => Err(e.into()),
+        Err(e) => Err(ApiError::BadRequest(e.to_string())),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    format: Option<WordListFormat>,
+}
+
+/// Outputs category's words as downloadable file in format accepted by
+/// `import_words`, plain text by default
+pub async fn export_words(
+    Extension(pool): Extension<SqlitePool>,
+    Path(category_id): Path<i64>,
+    SessionUser(user_id): SessionUser,
+    Query(export_query): Query<ExportQuery>,
+) -> ApiResult<Response> {
+    let format = export_query.format.unwrap_or(WordListFormat::Text);
+    if query_scalar!(
+        "select id from categories where id = ? and user_id = ?",
+        category_id,
+        user_id
+    )
+    .fetch_optional(&pool)
+    .await?
+    .is_none()
+    {
+        return Err(ApiError::NotFound("Category not found".to_owned()));
+    }
+
+    let words = query_scalar!(
+        "select word from words where category_id = ? order by created_at, id",
+        category_id
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"category-{}.{}\"",
+                    category_id,
+                    format.extension()
+                ),
+            ),
+        ],
+        word_lists::format_word_list(format, &words),
+    )
+        .into_response())
+}
+
 #[cfg(test)]
 mod test {
-    use axum::{extract::Path, Extension, Json};
+    use axum::{
+        http::{header, HeaderMap, HeaderValue, StatusCode},
+        Extension,
+    };
 
-    use crate::{api_data::WordCreateRequest, auth::SessionUser, test_utils::*};
+    use sqlx::query_scalar;
+
+    use super::{ExportQuery, ListWordsQuery, WordSort};
+    use crate::{
+        api_data::{WordCreateRequest, WordUpdateRequest, WordsBulkRequest},
+        auth::SessionUser,
+        controller::error::ApiError,
+        controller::extract::{Json, Path, Query},
+        test_utils::*,
+        word_lists::WordListFormat,
+    };
 
     #[tokio::test]
     async fn test_list_words_basic() {
@@ -108,14 +664,139 @@ mod test {
             add_test_word(&pool, category).await;
         }
 
-        let Json(words) = super::list_words(Extension(pool), Path(category), SessionUser(user))
-            .await
-            .expect("successful response");
+        let Json(words) = super::list_words(
+            Extension(pool),
+            Path(category),
+            SessionUser(user),
+            Query(ListWordsQuery::default()),
+        )
+        .await
+        .expect("successful response");
 
         assert_eq!(words.words.len(), 5);
+        assert_eq!(words.total, 5);
+        assert!(words.next_cursor.is_none());
         assert!(words.words.first().unwrap().id > 0);
     }
 
+    #[tokio::test]
+    async fn test_list_words_pages() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        let mut word_ids = vec![];
+        for _ in 0..5 {
+            word_ids.push(add_test_word(&pool, category).await);
+        }
+
+        let mut listed_ids = vec![];
+        let mut cursor = None;
+        loop {
+            let Json(words) = super::list_words(
+                Extension(pool.clone()),
+                Path(category),
+                SessionUser(user),
+                Query(ListWordsQuery {
+                    sort: WordSort::Oldest,
+                    cursor,
+                    limit: Some(2),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .expect("successful response");
+            assert_eq!(words.total, 5);
+            listed_ids.extend(words.words.iter().map(|w| w.id));
+            cursor = words.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        // Words share creation time, so order is by id
+        assert_eq!(listed_ids, word_ids);
+    }
+
+    #[tokio::test]
+    async fn test_list_words_cursor_of_other_sort() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        for _ in 0..3 {
+            add_test_word(&pool, category).await;
+        }
+
+        let Json(words) = super::list_words(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(user),
+            Query(ListWordsQuery {
+                sort: WordSort::Oldest,
+                limit: Some(2),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect("successful response");
+
+        let error = super::list_words(
+            Extension(pool),
+            Path(category),
+            SessionUser(user),
+            Query(ListWordsQuery {
+                sort: WordSort::Alphabetical,
+                cursor: words.next_cursor,
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        assert!(matches!(error, ApiError::BadRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_list_words_alphabetical_filtered() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        for word in ["ёлка", "ель", "елец", "берёза", "ясень"] {
+            add_test_named_word(&pool, category, word).await;
+        }
+
+        let Json(words) = super::list_words(
+            Extension(pool),
+            Path(category),
+            SessionUser(user),
+            Query(ListWordsQuery {
+                sort: WordSort::Alphabetical,
+                q: Some(" Е".to_owned()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect("successful response");
+        let words: Vec<_> = words.words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(words, vec!["берёза", "елец", "ель", "ясень"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_words_not_member() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        add_test_word(&pool, category).await;
+
+        let error = super::list_words(
+            Extension(pool),
+            Path(category),
+            SessionUser(user2),
+            Query(ListWordsQuery::default()),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        assert!(matches!(error, ApiError::NotFound(_)));
+    }
+
     #[tokio::test]
     async fn test_create_word_basic() {
         let pool = test_database_pool().await;
@@ -128,6 +809,7 @@ mod test {
             SessionUser(user),
             Json(WordCreateRequest {
                 word: "foo".to_owned(),
+                ..Default::default()
             }),
         )
         .await
@@ -137,6 +819,265 @@ mod test {
         assert_eq!("foo", word.word);
     }
 
+    #[tokio::test]
+    async fn test_create_word_validation() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+
+        let Json(word) = super::create_word(
+            Extension(pool.clone()),
+            Path(category),
+            SessionUser(user),
+            Json(WordCreateRequest {
+                word: "  Снегирь ".to_owned(),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(word.word, "снегирь");
+
+        let error = super::create_word(
+            Extension(pool),
+            Path(category),
+            SessionUser(user),
+            Json(WordCreateRequest {
+                word: "снегирь!".to_owned(),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        match error {
+            ApiError::Validation(_, Some(details)) => {
+                assert_eq!(details["fields"][0]["field"], "word");
+                assert_eq!(details["fields"][0]["code"], "invalid_character");
+            }
+            _ => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_word_duplicate() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        let existing = add_test_named_word(&pool, category, "снегирь").await;
+
+        let error = super::create_word(
+            Extension(pool),
+            Path(category),
+            SessionUser(user),
+            Json(WordCreateRequest {
+                word: "Снегирь".to_owned(),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        match error {
+            ApiError::Conflict(_, Some(details)) => {
+                assert_eq!(details["existing_word_id"], existing)
+            }
+            _ => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_word_error_after_race() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        let existing = add_test_named_word(&pool, category, "снегирь").await;
+
+        // Insert which passed uniqueness check before concurrent one committed
+        let error = sqlx::query(
+            "insert into words (category_id, word, created_at, updated_at) values (?, ?, 0, 0)",
+        )
+        .bind(category)
+        .bind("снегирь")
+        .execute(&pool)
+        .await
+        .expect_err("unique violation");
+        match super::write_word_error(&pool, category, "снегирь", error).await {
+            ApiError::Conflict(_, Some(details)) => {
+                assert_eq!(details["existing_word_id"], existing)
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_word() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        let word = add_test_named_word(&pool, category, "снигирь").await;
+        add_test_named_word(&pool, category, "сипуха").await;
+
+        let Json(updated) = super::update_word(
+            Extension(pool.clone()),
+            Path((category, word)),
+            SessionUser(user1),
+            Json(WordUpdateRequest {
+                word: Some("снегирь".to_owned()),
+                difficulty: Some(Some(2)),
+                tags: Some(vec!["птицы".to_owned()]),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(updated.id, word);
+        assert_eq!(updated.word, "снегирь");
+        assert_eq!(updated.difficulty, Some(2));
+        assert_eq!(updated.tags.0, vec!["птицы"]);
+        assert_eq!(updated.created_at.year(), 2022);
+
+        let Json(updated) = super::update_word(
+            Extension(pool.clone()),
+            Path((category, word)),
+            SessionUser(user1),
+            Json(serde_json::from_str(r#"{"difficulty": null}"#).unwrap()),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(updated.difficulty, None);
+        assert_eq!(updated.tags.0, vec!["птицы"]);
+
+        let error = super::update_word(
+            Extension(pool.clone()),
+            Path((category, word)),
+            SessionUser(user1),
+            Json(WordUpdateRequest {
+                word: Some("сипуха".to_owned()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        assert!(matches!(error, ApiError::Conflict(..)));
+
+        let error = super::update_word(
+            Extension(pool),
+            Path((category, word)),
+            SessionUser(user2),
+            Json(WordUpdateRequest::default()),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        assert!(matches!(error, ApiError::NotFound(_)));
+    }
+
+    fn bulk_request(json: &str) -> Json<WordsBulkRequest> {
+        Json(serde_json::from_str(json).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_move_and_copy() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let source = add_test_category(&pool, user).await;
+        let target = add_test_category(&pool, user).await;
+        let moved = add_test_named_word(&pool, source, "снегирь").await;
+        let skipped = add_test_named_word(&pool, source, "сипуха").await;
+        add_test_named_word(&pool, target, "сипуха").await;
+
+        let Json(report) = super::bulk_words(
+            Extension(pool.clone()),
+            SessionUser(user),
+            bulk_request(&format!(
+                r#"{{"action": "move", "target_category_id": {}, "word_ids": [{}, {}]}}"#,
+                target, moved, skipped
+            )),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(report.affected, 1);
+        assert_eq!(report.skipped_word_ids, vec![skipped]);
+
+        let Json(report) = super::bulk_words(
+            Extension(pool.clone()),
+            SessionUser(user),
+            bulk_request(&format!(
+                r#"{{"action": "copy", "target_category_id": {}, "word_ids": [{}]}}"#,
+                source, moved
+            )),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(report.affected, 1);
+
+        let source_words = query_scalar!(
+            "select word from words where category_id = ? order by word",
+            source
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(source_words, vec!["сипуха", "снегирь"]);
+        let target_count =
+            query_scalar!("select count(*) from words where category_id = ?", target)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(target_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_ownership() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category1 = add_test_category(&pool, user1).await;
+        let category2 = add_test_category(&pool, user2).await;
+        let word1 = add_test_word(&pool, category1).await;
+        let word2 = add_test_word(&pool, category2).await;
+
+        // Foreign word
+        super::bulk_words(
+            Extension(pool.clone()),
+            SessionUser(user1),
+            bulk_request(&format!(
+                r#"{{"action": "delete", "word_ids": [{}, {}]}}"#,
+                word1, word2
+            )),
+        )
+        .await
+        .expect_err("unsuccessful response");
+        // Foreign target category
+        super::bulk_words(
+            Extension(pool.clone()),
+            SessionUser(user1),
+            bulk_request(&format!(
+                r#"{{"action": "move", "target_category_id": {}, "word_ids": [{}]}}"#,
+                category2, word1
+            )),
+        )
+        .await
+        .expect_err("unsuccessful response");
+
+        let words_count = query_scalar!("select count(*) from words")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(words_count, 2);
+
+        let Json(report) = super::bulk_words(
+            Extension(pool.clone()),
+            SessionUser(user1),
+            bulk_request(&format!(
+                r#"{{"action": "delete", "word_ids": [{}]}}"#,
+                word1
+            )),
+        )
+        .await
+        .expect("successful response");
+        assert_eq!(report.affected, 1);
+    }
+
     #[tokio::test]
     async fn test_create_word_other_users_category() {
         let pool = test_database_pool().await;
@@ -150,6 +1091,7 @@ mod test {
             SessionUser(user2),
             Json(WordCreateRequest {
                 word: "foo".to_owned(),
+                ..Default::default()
             }),
         )
         .await
@@ -164,32 +1106,109 @@ mod test {
         let category = add_test_category(&pool, user).await;
         let word = add_test_word(&pool, category).await;
 
-        super::delete_word(
+        super::delete_word(Extension(pool), Path((category, word)), SessionUser(user))
+            .await
+            .expect("successful response");
+    }
+
+    #[tokio::test]
+    async fn test_delete_word_in_game() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        let word = add_test_word(&pool, category).await;
+        let game = add_test_game(&pool, user1, user2).await;
+        add_test_game_word(&pool, game, word).await;
+
+        let error = super::delete_word(Extension(pool), Path((category, word)), SessionUser(user1))
+            .await
+            .expect_err("unsuccessful response");
+        assert!(matches!(error, ApiError::Conflict(..)));
+    }
+
+    #[tokio::test]
+    async fn test_delete_word_another_user() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        let word = add_test_word(&pool, category).await;
+
+        super::delete_word(Extension(pool), Path((category, word)), SessionUser(user2))
+            .await
+            .expect_err("unsuccessful response");
+        // TODO: check response code somehow
+    }
+
+    #[tokio::test]
+    async fn test_import_words_json() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+
+        let Json(report) = super::import_words(
             Extension(pool),
             Path(category),
-            Path(word),
             SessionUser(user),
+            headers,
+            r#"["снегирь", "сипуха", "снегирь", ""]"#.to_owned(),
         )
         .await
         .expect("successful response");
+
+        assert_eq!(report.added, 2);
+        assert_eq!(report.duplicate, 1);
+        assert_eq!(report.invalid, 1);
     }
 
     #[tokio::test]
-    async fn test_delete_word_another_user() {
+    async fn test_import_words_other_users_category() {
         let pool = test_database_pool().await;
         let user1 = add_test_user(&pool, "user1").await;
         let user2 = add_test_user(&pool, "user2").await;
         let category = add_test_category(&pool, user1).await;
-        let word = add_test_word(&pool, category).await;
 
-        super::delete_word(
+        super::import_words(
             Extension(pool),
             Path(category),
-            Path(word),
             SessionUser(user2),
+            HeaderMap::new(),
+            "снегирь".to_owned(),
         )
         .await
         .expect_err("unsuccessful response");
-        // TODO: check response code somehow
+    }
+
+    #[tokio::test]
+    async fn test_export_words_csv() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        add_test_named_word(&pool, category, "снегирь").await;
+
+        let response = super::export_words(
+            Extension(pool),
+            Path(category),
+            SessionUser(user),
+            Query(ExportQuery {
+                format: Some(WordListFormat::Csv),
+            }),
+        )
+        .await
+        .expect("successful response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "word\nснегирь\n");
     }
 }
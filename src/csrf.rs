@@ -0,0 +1,293 @@
+use axum::{
+    http::{header, HeaderMap, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use base64::Engine;
+use rand::{thread_rng, RngCore};
+use tracing::warn;
+
+use crate::auth::{bearer_token, CookieSettings};
+use crate::controller::error::ApiError;
+
+/// Cookie holding CSRF token. Readable by JavaScript, which copies it to
+/// `CSRF_HEADER_NAME` header.
+pub const CSRF_COOKIE_NAME: &str = "pss_csrf";
+pub const CSRF_HEADER_NAME: &str = "x-csrf-token";
+
+pub fn new_csrf_token() -> String {
+    let mut token = [0u8; 32];
+    thread_rng().fill_bytes(&mut token);
+    base64::engine::general_purpose::URL_SAFE.encode(token)
+}
+
+pub fn csrf_cookie(token: String, settings: &CookieSettings) -> Cookie<'static> {
+    settings.cookie(CSRF_COOKIE_NAME, token)
+}
+
+/// CSRF token from request cookies, if present
+pub fn csrf_cookie_token(cookies: &CookieJar) -> Option<&str> {
+    cookies.get(CSRF_COOKIE_NAME).map(|cookie| cookie.value())
+}
+
+/// Compares tokens in constant time
+pub fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// `host[:port]` part of URL like `https://host:port/path`
+fn url_authority(url: &str) -> Option<&str> {
+    let (_scheme, rest) = url.split_once("://")?;
+    rest.split('/').next()
+}
+
+/// Checks that `Origin` header, or `Referer` if there is no `Origin`, points
+/// to the same host request was sent to. Requests with neither header are
+/// assumed to come from non-browser clients.
+fn same_origin(headers: &HeaderMap) -> bool {
+    let host = headers.get(header::HOST).and_then(|h| h.to_str().ok());
+    let source = headers
+        .get(header::ORIGIN)
+        .or_else(|| headers.get(header::REFERER))
+        .map(|h| h.to_str().ok());
+
+    match (source, host) {
+        (None, _) => true,
+        (Some(Some(source)), Some(host)) => url_authority(source) == Some(host),
+        _ => false,
+    }
+}
+
+/// Rejects cross-origin state-changing requests. API requests must
+/// additionally repeat CSRF cookie in `CSRF_HEADER_NAME` header, unless they
+/// are authenticated with bearer token, which browsers never send on their
+/// own; HTML forms check the token themselves as it's sent in request body.
+pub async fn csrf_protection<B>(request: Request<B>, next: Next<B>) -> Response {
+    if request.method().is_safe() {
+        return next.run(request).await;
+    }
+
+    if !same_origin(request.headers()) {
+        warn!(
+            "Rejected cross-origin {} {}",
+            request.method(),
+            request.uri()
+        );
+        return ApiError::Forbidden("Cross-origin request rejected".to_owned()).into_response();
+    }
+
+    if request.uri().path().starts_with("/api/") && bearer_token(request.headers()).is_none() {
+        let cookies = CookieJar::from_headers(request.headers());
+        let header_token = request
+            .headers()
+            .get(CSRF_HEADER_NAME)
+            .and_then(|h| h.to_str().ok());
+        match (csrf_cookie_token(&cookies), header_token) {
+            (Some(cookie_token), Some(header_token))
+                if tokens_match(cookie_token, header_token) => {}
+            _ => return ApiError::Forbidden("Invalid CSRF token".to_owned()).into_response(),
+        }
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use crate::{app, auth::CookieSettings, test_utils::*, users::create_session};
+
+    async fn test_app() -> (Router, String) {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let session_secret = create_session(&pool, user, "test").await.unwrap();
+        (app(pool, CookieSettings::default()), session_secret)
+    }
+
+    fn create_category_request(session_secret: &str) -> axum::http::request::Builder {
+        Request::post("/api/v1/words")
+            .header(header::HOST, "localhost:8080")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(
+                header::COOKIE,
+                format!("pss_session={}; pss_csrf=token", session_secret),
+            )
+    }
+
+    #[tokio::test]
+    async fn test_same_origin_with_token_accepted() {
+        let (app, session_secret) = test_app().await;
+        let response = app
+            .oneshot(
+                create_category_request(&session_secret)
+                    .header(header::ORIGIN, "http://localhost:8080")
+                    .header("x-csrf-token", "token")
+                    .body(Body::from(r#"{"name": "test"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cross_origin_rejected() {
+        let (app, session_secret) = test_app().await;
+        let response = app
+            .oneshot(
+                create_category_request(&session_secret)
+                    .header(header::ORIGIN, "https://evil.example")
+                    .header("x-csrf-token", "token")
+                    .body(Body::from(r#"{"name": "test"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_cross_origin_referer_rejected() {
+        let (app, session_secret) = test_app().await;
+        let response = app
+            .oneshot(
+                create_category_request(&session_secret)
+                    .header(header::REFERER, "https://evil.example/page")
+                    .header("x-csrf-token", "token")
+                    .body(Body::from(r#"{"name": "test"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_missing_token_rejected() {
+        let (app, session_secret) = test_app().await;
+        let response = app
+            .oneshot(
+                create_category_request(&session_secret)
+                    .body(Body::from(r#"{"name": "test"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_cross_origin_login_rejected() {
+        let (app, _) = test_app().await;
+        let response = app
+            .oneshot(
+                Request::post("/auth/login")
+                    .header(header::HOST, "localhost:8080")
+                    .header(header::ORIGIN, "https://evil.example")
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from("username=user&password=123&csrf_token=x"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_login_form_token_mismatch_rejected() {
+        let (app, _) = test_app().await;
+        let response = app
+            .oneshot(
+                Request::post("/auth/login")
+                    .header(header::HOST, "localhost:8080")
+                    .header(header::ORIGIN, "http://localhost:8080")
+                    .header(header::USER_AGENT, "test")
+                    .header(header::COOKIE, "pss_csrf=token")
+                    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                    .body(Body::from("username=user&password=123&csrf_token=other"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    fn logout_request(session_secret: &str) -> axum::http::request::Builder {
+        Request::post("/auth/logout")
+            .header(header::HOST, "localhost:8080")
+            .header(
+                header::COOKIE,
+                format!("pss_session={}; pss_csrf=token", session_secret),
+            )
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+    }
+
+    #[tokio::test]
+    async fn test_cross_origin_logout_rejected() {
+        let (app, session_secret) = test_app().await;
+        let response = app
+            .oneshot(
+                logout_request(&session_secret)
+                    .header(header::ORIGIN, "https://evil.example")
+                    .body(Body::from("csrf_token=token"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_logout_form_token_mismatch_rejected() {
+        let (app, session_secret) = test_app().await;
+        let response = app
+            .clone()
+            .oneshot(
+                logout_request(&session_secret)
+                    .body(Body::from("csrf_token=other"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .oneshot(
+                logout_request(&session_secret)
+                    .body(Body::from("csrf_token=token"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_bearer_token_with_session_rejected() {
+        let (app, session_secret) = test_app().await;
+        let response = app
+            .oneshot(
+                Request::post("/api/v1/tokens")
+                    .header(header::HOST, "localhost:8080")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::COOKIE, format!("pss_session={}", session_secret))
+                    .header(header::AUTHORIZATION, "Bearer invalid")
+                    .body(Body::from(r#"{"name": "test", "scope": "read_write"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,54 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use sqlx::sqlite::SqliteConnectOptions;
+
+/// Name of collation ordering words as in Russian dictionaries
+pub const RUSSIAN_COLLATION: &str = "russian";
+/// Extended result code reported by SQLite on unique index violation
+const SQLITE_CONSTRAINT_UNIQUE: &str = "2067";
+
+/// Connection options shared by server and tests: enforced foreign keys
+/// and custom collations
+pub fn connect_options(database_url: &str) -> sqlx::Result<SqliteConnectOptions> {
+    Ok(SqliteConnectOptions::from_str(database_url)?
+        .foreign_keys(true)
+        .collation(RUSSIAN_COLLATION, russian_cmp))
+}
+
+/// Whether query failed because it violated unique index
+pub fn is_unique_violation(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Database(e) if e.code().as_deref() == Some(SQLITE_CONSTRAINT_UNIQUE))
+}
+
+fn russian_primary(c: char) -> char {
+    match c {
+        'ё' | 'Ё' => 'е',
+        c => c.to_lowercase().next().unwrap_or(c),
+    }
+}
+
+/// Compares case-insensitively, with `ё` sorted along with `е` instead of
+/// after `я` as in code points. Strings equal in this sense are ordered by
+/// code points, so that order is total.
+pub fn russian_cmp(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .map(russian_primary)
+        .cmp(b.chars().map(russian_primary))
+        .then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::russian_cmp;
+
+    #[test]
+    fn test_russian_cmp() {
+        let mut words = vec!["яблоко", "ёж", "ель", "Жук", "еж", "ёлка", "apple"];
+        words.sort_by(|a, b| russian_cmp(a, b));
+        assert_eq!(
+            words,
+            vec!["apple", "еж", "ёж", "ёлка", "ель", "Жук", "яблоко"]
+        );
+    }
+}
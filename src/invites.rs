@@ -0,0 +1,86 @@
+use base64::Engine;
+use rand::{thread_rng, RngCore};
+use sqlx::{query, types::time::OffsetDateTime, SqliteConnection, SqlitePool};
+
+use crate::users::secret_digest;
+
+fn new_invite_code() -> String {
+    let mut code = [0u8; 12];
+    thread_rng().fill_bytes(&mut code);
+    base64::engine::general_purpose::URL_SAFE.encode(code)
+}
+
+/// Creates invite code which can be used for registration `uses` times
+/// before `expires_at`. Like session secrets, only digest is stored.
+pub async fn create_invite(
+    pool: &SqlitePool,
+    uses: u32,
+    expires_at: Option<OffsetDateTime>,
+) -> sqlx::Result<String> {
+    let code = new_invite_code();
+    let code_digest = secret_digest(&code);
+    let now = OffsetDateTime::now_utc();
+    query!(
+        "insert into invites (code_digest, uses_left, expires_at, created_at) values (?, ?, ?, ?)",
+        code_digest,
+        uses,
+        expires_at,
+        now
+    )
+    .execute(pool)
+    .await?;
+    Ok(code)
+}
+
+/// Uses invite code once, returning false if code is unknown, used up or expired
+pub async fn consume_invite(conn: &mut SqliteConnection, code: &str) -> sqlx::Result<bool> {
+    let code_digest = secret_digest(code);
+    let now = OffsetDateTime::now_utc();
+    // Single statement, so that concurrent uses can't both take the last one
+    let updated = query!(
+        "update invites set uses_left = uses_left - 1
+        where code_digest = ? and uses_left > 0
+          and (expires_at is null or julianday(expires_at) > julianday(?))",
+        code_digest,
+        now
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    Ok(updated == 1)
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::types::time::OffsetDateTime;
+    use time::Duration;
+
+    use super::{consume_invite, create_invite};
+    use crate::test_utils::*;
+
+    #[tokio::test]
+    async fn test_invite_uses() {
+        let pool = test_database_pool().await;
+        let code = create_invite(&pool, 2, None).await.unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+
+        assert!(consume_invite(&mut conn, &code).await.unwrap());
+        assert!(consume_invite(&mut conn, &code).await.unwrap());
+        assert!(!consume_invite(&mut conn, &code).await.unwrap());
+        assert!(!consume_invite(&mut conn, "unknown").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_invite_expired() {
+        let pool = test_database_pool().await;
+        let expired_at = OffsetDateTime::now_utc() - Duration::days(1);
+        let code = create_invite(&pool, 1, Some(expired_at)).await.unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+
+        assert!(!consume_invite(&mut conn, &code).await.unwrap());
+
+        let expires_at = OffsetDateTime::now_utc() + Duration::days(1);
+        let code = create_invite(&pool, 1, Some(expires_at)).await.unwrap();
+        assert!(consume_invite(&mut conn, &code).await.unwrap());
+    }
+}
@@ -0,0 +1,194 @@
+use sqlx::{query, query_scalar, types::time::OffsetDateTime, SqlitePool};
+use time::Duration;
+
+/// Failed attempts per username allowed without delay
+pub const FREE_ATTEMPTS_PER_USERNAME: usize = 3;
+/// Failed attempts per IP allowed without delay, higher as one address may
+/// be shared by several users
+pub const FREE_ATTEMPTS_PER_IP: usize = 10;
+pub const BASE_DELAY: Duration = Duration::seconds(1);
+/// Delay doesn't grow beyond this, effectively being temporary lockout
+pub const MAX_DELAY: Duration = Duration::minutes(15);
+/// Failed attempts older than this are not counted
+pub const ATTEMPTS_WINDOW: Duration = Duration::hours(24);
+/// Only this many latest failures are considered, delay is already maximal
+const MAX_COUNTED_ATTEMPTS: i64 = 64;
+
+/// Delay required after last of `failures` consecutive failed attempts
+pub fn backoff_delay(failures: usize, free_attempts: usize) -> Option<Duration> {
+    if failures < free_attempts {
+        return None;
+    }
+    let exponent = (failures - free_attempts).min(20) as i32;
+    Some((BASE_DELAY * 2i32.pow(exponent as u32)).min(MAX_DELAY))
+}
+
+/// Time left until next attempt is allowed, given times of failed attempts,
+/// latest first
+fn retry_after(
+    failures: &[OffsetDateTime],
+    free_attempts: usize,
+    now: OffsetDateTime,
+) -> Option<Duration> {
+    let recent: Vec<&OffsetDateTime> = failures
+        .iter()
+        .take_while(|t| now - **t < ATTEMPTS_WINDOW)
+        .collect();
+    let last = **recent.first()?;
+    let delay = backoff_delay(recent.len(), free_attempts)?;
+    let left = last + delay - now;
+    (left > Duration::ZERO).then_some(left)
+}
+
+/// Time left until attempt with id `attempt_id` is allowed, considering
+/// failures recorded before it
+async fn check_before(
+    pool: &SqlitePool,
+    username: &str,
+    ip: &str,
+    attempt_id: i64,
+) -> sqlx::Result<Option<Duration>> {
+    let now = OffsetDateTime::now_utc();
+
+    let username_failures = query_scalar!(
+        r#"select created_at as "created_at: OffsetDateTime" from login_attempts
+        where username = ? and id < ? order by id desc limit ?"#,
+        username,
+        attempt_id,
+        MAX_COUNTED_ATTEMPTS
+    )
+    .fetch_all(pool)
+    .await?;
+    let ip_failures = query_scalar!(
+        r#"select created_at as "created_at: OffsetDateTime" from login_attempts
+        where ip = ? and id < ? order by id desc limit ?"#,
+        ip,
+        attempt_id,
+        MAX_COUNTED_ATTEMPTS
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(
+        match (
+            retry_after(&username_failures, FREE_ATTEMPTS_PER_USERNAME, now),
+            retry_after(&ip_failures, FREE_ATTEMPTS_PER_IP, now),
+        ) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        },
+    )
+}
+
+/// Records login attempt for username from IP as failed before password is
+/// verified, so that concurrent attempts can't all pass the check. Successful
+/// login clears it along with earlier failures.
+///
+/// If attempt has to be rejected, it's not recorded and time left until next
+/// attempt is allowed is returned.
+pub async fn record_attempt(
+    pool: &SqlitePool,
+    username: &str,
+    ip: &str,
+) -> sqlx::Result<Option<Duration>> {
+    let now = OffsetDateTime::now_utc();
+    let expired_before = now - ATTEMPTS_WINDOW;
+    query!(
+        "delete from login_attempts where julianday(created_at) < julianday(?)",
+        expired_before
+    )
+    .execute(pool)
+    .await?;
+
+    let attempt_id = query!(
+        "insert into login_attempts (username, ip, created_at) values (?, ?, ?)",
+        username,
+        ip,
+        now
+    )
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    let retry_after = check_before(pool, username, ip, attempt_id).await?;
+    if retry_after.is_some() {
+        query!("delete from login_attempts where id = ?", attempt_id)
+            .execute(pool)
+            .await?;
+    }
+    Ok(retry_after)
+}
+
+/// Forgets failed attempts for username, returning number of forgotten attempts
+pub async fn clear_failures(pool: &SqlitePool, username: &str) -> sqlx::Result<u64> {
+    Ok(
+        query!("delete from login_attempts where username = ?", username)
+            .execute(pool)
+            .await?
+            .rows_affected(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::{query, types::time::OffsetDateTime};
+    use time::Duration;
+
+    use super::{
+        backoff_delay, clear_failures, record_attempt, ATTEMPTS_WINDOW, FREE_ATTEMPTS_PER_USERNAME,
+        MAX_DELAY,
+    };
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        assert_eq!(backoff_delay(2, 3), None);
+        assert_eq!(backoff_delay(3, 3), Some(Duration::seconds(1)));
+        assert_eq!(backoff_delay(5, 3), Some(Duration::seconds(4)));
+        assert_eq!(backoff_delay(1000, 3), Some(MAX_DELAY));
+    }
+
+    #[tokio::test]
+    async fn test_lockout_and_clear() {
+        let pool = test_database_pool().await;
+
+        for _ in 0..FREE_ATTEMPTS_PER_USERNAME {
+            assert!(record_attempt(&pool, "user", "127.0.0.1")
+                .await
+                .unwrap()
+                .is_none());
+        }
+        assert!(record_attempt(&pool, "user", "127.0.0.2")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(record_attempt(&pool, "user2", "127.0.0.2")
+            .await
+            .unwrap()
+            .is_none());
+
+        // Rejected attempt isn't recorded
+        assert_eq!(
+            clear_failures(&pool, "user").await.unwrap(),
+            FREE_ATTEMPTS_PER_USERNAME as u64
+        );
+        assert!(record_attempt(&pool, "user", "127.0.0.2")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_attempts_pruned() {
+        let pool = test_database_pool().await;
+        let expired = OffsetDateTime::now_utc() - ATTEMPTS_WINDOW - Duration::minutes(1);
+        query("insert into login_attempts (username, ip, created_at) values ('old', '', ?)")
+            .bind(expired)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        record_attempt(&pool, "user", "").await.unwrap();
+        assert_eq!(clear_failures(&pool, "old").await.unwrap(), 0);
+    }
+}
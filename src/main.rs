@@ -1,23 +1,44 @@
-use std::{net::SocketAddr, process::ExitCode, sync::Arc};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
 
+use anyhow::anyhow;
+use auth::CookieSettings;
 use axum::{
+    middleware,
     routing::{delete, get, patch, post},
     Extension, Router,
 };
+use axum_server::tls_rustls::RustlsConfig;
 use clap::{Parser, Subcommand};
+use config::Config;
 use handlebars::Handlebars;
 use rust_embed::RustEmbed;
-use schema::install_schema;
-use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+use sqlx::{query_scalar, sqlite::SqlitePoolOptions, types::time::OffsetDateTime, SqlitePool};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
 
 mod api_data;
+mod api_tokens;
 mod auth;
+mod category_members;
+mod config;
 mod controller;
+mod csrf;
+mod db;
+mod invites;
+mod login_throttle;
+mod request_id;
 mod schema;
 mod seeds;
+mod share_links;
 #[cfg(test)]
 mod test_utils;
 mod users;
+mod validation;
+mod word_lists;
 
 #[derive(RustEmbed)]
 #[folder = "templates"]
@@ -43,27 +64,122 @@ fn routes() -> Router {
             "/words/:category_id",
             delete(controller::categories::delete_category),
         )
+        .route(
+            "/words/reorder",
+            post(controller::categories::reorder_categories),
+        )
+        .route("/words/bulk", post(controller::words::bulk_words))
         .route("/words/:category_id", get(controller::words::list_words))
         .route("/words/:category_id", post(controller::words::create_word))
         .route(
             "/words/:category_id/:word_id",
             delete(controller::words::delete_word),
-        );
+        )
+        .route(
+            "/words/:category_id/:word_id",
+            patch(controller::words::update_word),
+        )
+        .route(
+            "/words/:category_id/members",
+            get(controller::category_members::list_members),
+        )
+        .route(
+            "/words/:category_id/members",
+            post(controller::category_members::add_member),
+        )
+        .route(
+            "/words/:category_id/members/:username",
+            delete(controller::category_members::remove_member),
+        )
+        .route(
+            "/words/:category_id/share",
+            get(controller::share_links::list_share_links),
+        )
+        .route(
+            "/words/:category_id/share",
+            post(controller::share_links::create_share_link),
+        )
+        .route(
+            "/words/:category_id/share/:link_id",
+            delete(controller::share_links::revoke_share_link),
+        )
+        .route(
+            "/words/:category_id/import",
+            post(controller::words::import_words),
+        )
+        .route(
+            "/words/:category_id/export",
+            get(controller::words::export_words),
+        )
+        .route("/sessions", get(controller::sessions::list_sessions))
+        .route(
+            "/sessions/:session_id",
+            delete(controller::sessions::delete_session),
+        )
+        .route("/duplicates", get(controller::duplicates::list_duplicates))
+        .route(
+            "/duplicates/merge",
+            post(controller::duplicates::merge_duplicates),
+        )
+        .route("/search", get(controller::search::search))
+        .route("/tokens", get(controller::api_tokens::list_tokens))
+        .route("/tokens", post(controller::api_tokens::create_token))
+        .route(
+            "/tokens/:token_id",
+            delete(controller::api_tokens::delete_token),
+        )
+        .route("/export", get(controller::archive::export_archive))
+        .route("/import", post(controller::archive::import_archive))
+        .route("/games", get(controller::games::list_games))
+        .route("/games", post(controller::games::create_game))
+        .route("/games/:game_id", get(controller::games::get_game))
+        .route(
+            "/games/:game_id/finish",
+            post(controller::games::finish_game),
+        )
+        .route(
+            "/games/:game_id/composition",
+            get(controller::compositions::get_composition),
+        )
+        .route(
+            "/games/:game_id/composition",
+            post(controller::compositions::submit_composition),
+        )
+        .fallback(controller::error::not_found);
 
     let auth_routes = Router::new()
         .route("/login", get(controller::auth::login_page))
-        .route("/login", post(controller::auth::login_submit));
+        .route("/login", post(controller::auth::login_submit))
+        .route("/logout", post(controller::auth::logout))
+        .route("/register", get(controller::auth::register_page))
+        .route("/register", post(controller::auth::register_submit));
 
     Router::new()
         .nest("/api/v1", api_routes)
         .nest("/auth", auth_routes)
+        .route("/s/:token", get(controller::share_links::shared_category))
+}
+
+/// Application with all routes, middleware and shared state
+fn app(pool: SqlitePool, cookie_settings: CookieSettings) -> Router {
+    routes()
+        .layer(middleware::from_fn(csrf::csrf_protection))
+        .layer(middleware::from_fn(request_id::request_id))
+        .layer(Extension(pool))
+        .layer(Extension(cookie_settings))
+        .layer(Extension(Arc::new(make_handlebars())))
 }
 
 #[derive(Parser)]
 #[command(version, about)]
 struct Cli {
-    #[arg(long, default_value = "development.sqlite")]
-    database: String,
+    /// TOML configuration file
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Overrides database_url from configuration
+    #[arg(long)]
+    database: Option<String>,
 
     #[command(subcommand)]
     command: Commands,
@@ -72,66 +188,315 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     Start {
-        #[arg(long, default_value = "8080")]
-        port: u16,
+        /// Overrides port from configuration
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// Apply pending database migrations before starting server
+        #[arg(long)]
+        migrate: bool,
+
+        /// Mark cookies as Secure, same as `cookie.secure` in configuration
+        #[arg(long)]
+        secure_cookies: bool,
     },
     User {
         #[command(subcommand)]
         command: UserCommands,
     },
+    Invite {
+        #[command(subcommand)]
+        command: InviteCommands,
+    },
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
+    },
     Db {
         #[command(subcommand)]
         command: DbCommands,
     },
+    Words {
+        #[command(subcommand)]
+        command: WordsCommands,
+    },
+    /// Export all categories and words of user as JSON archive
+    Export {
+        #[arg(long)]
+        user: String,
+        /// Output file, stdout if not specified
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Create categories and words for user from JSON archive
+    Import {
+        #[arg(long)]
+        user: String,
+        file: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
 enum UserCommands {
-    Add { username: String, password: String },
-    SetPassword { username: String, password: String },
+    Add {
+        username: String,
+        password: String,
+    },
+    SetPassword {
+        username: String,
+        password: String,
+    },
+    /// Forget failed login attempts, lifting lockout
+    Unlock {
+        username: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum InviteCommands {
+    /// Create invite code for registration and print it
+    Create {
+        /// How many users can register with the code
+        #[arg(long, default_value = "1")]
+        uses: u32,
+        /// Days until the code expires, never if not specified
+        #[arg(long, value_name = "DAYS")]
+        expires: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommands {
+    /// Create API token for user and print its secret
+    Create {
+        #[arg(long)]
+        user: String,
+        #[arg(long)]
+        name: String,
+        #[arg(long, value_enum, default_value = "read-write")]
+        scope: api_tokens::TokenScope,
+    },
+    /// List user's API tokens
+    List {
+        #[arg(long)]
+        user: String,
+    },
+    /// Revoke user's API token
+    Revoke {
+        #[arg(long)]
+        user: String,
+        /// Token id
+        id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum WordsCommands {
+    /// Add words from file to category
+    Import {
+        #[arg(long)]
+        user: String,
+        /// Category id
+        #[arg(long)]
+        category: i64,
+        /// Format of file, guessed by extension if not specified
+        #[arg(long, value_enum)]
+        format: Option<word_lists::WordListFormat>,
+        file: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
 enum DbCommands {
-    Init,
+    /// Apply all pending migrations
+    Migrate,
+    /// Show applied and pending migrations
+    Status,
+    /// Revert latest applied migrations
+    Rollback {
+        #[arg(long, default_value = "1")]
+        steps: usize,
+    },
     Seed,
 }
 
-async fn create_pool(database: &str) -> SqlitePool {
+async fn create_pool(config: &Config) -> SqlitePool {
+    let options = db::connect_options(&config.database_url).expect("invalid database url");
     SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(&database)
+        .max_connections(config.pool_size)
+        .connect_with(options)
         .await
         .expect("couldn't connect to database")
 }
 
-async fn start_server(port: u16, pool: SqlitePool) {
-    let app = routes()
-        .layer(Extension(pool))
-        .layer(Extension(Arc::new(make_handlebars())));
+async fn start_server(config: &Config, pool: SqlitePool) -> anyhow::Result<()> {
+    let app = app(pool, config.cookie.clone());
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    tracing::info!("listening on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    let addr = config.socket_addr();
+    match &config.tls {
+        Some(tls) => {
+            let rustls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path).await?;
+            tracing::info!("listening on https://{}", addr);
+            axum_server::bind_rustls(addr, rustls_config)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+        }
+        None => {
+            tracing::info!("listening on http://{}", addr);
+            axum::Server::bind(&addr)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn token_command(pool: &SqlitePool, command: TokenCommands) -> anyhow::Result<()> {
+    let find_user = |username: String| async move {
+        users::user_by_name(pool, &username)
+            .await?
+            .ok_or_else(|| anyhow!("user {} not found", username))
+    };
+    match command {
+        TokenCommands::Create { user, name, scope } => {
+            let user_id = find_user(user).await?;
+            let token = api_tokens::create_token(pool, user_id, &name, scope).await?;
+            println!("{}", token.secret);
+        }
+        TokenCommands::List { user } => {
+            let user_id = find_user(user).await?;
+            for token in api_tokens::list_tokens(pool, user_id).await? {
+                println!(
+                    "{}\t{}\t{:?}\t{}",
+                    token.id, token.name, token.scope, token.created_at
+                );
+            }
+        }
+        TokenCommands::Revoke { user, id } => {
+            let user_id = find_user(user).await?;
+            if !api_tokens::delete_token(pool, user_id, id).await? {
+                return Err(anyhow!("token {} not found", id));
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn import_words_file(
+    pool: &SqlitePool,
+    username: &str,
+    category_id: i64,
+    format: Option<word_lists::WordListFormat>,
+    file: &Path,
+) -> anyhow::Result<api_data::WordImportReport> {
+    let user_id = users::user_by_name(pool, username)
+        .await?
+        .ok_or_else(|| anyhow!("user {} not found", username))?;
+    query_scalar!(
+        "select id from categories where id = ? and user_id = ?",
+        category_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("category {} of user {} not found", category_id, username))?;
+
+    let format = format.unwrap_or_else(|| word_lists::WordListFormat::from_path(file));
+    let data = tokio::fs::read_to_string(file).await?;
+    let entries = word_lists::parse_word_list(format, &data)?;
+    Ok(word_lists::import_words(pool, category_id, entries).await?)
+}
+
+async fn export_archive_file(
+    pool: &SqlitePool,
+    username: &str,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let user_id = users::user_by_name(pool, username)
+        .await?
+        .ok_or_else(|| anyhow!("user {} not found", username))?;
+    let archive = word_lists::export_archive(pool, user_id).await?;
+    let data = serde_json::to_string_pretty(&archive)?;
+    match output {
+        Some(path) => tokio::fs::write(path, data).await?,
+        None => println!("{}", data),
+    }
+    Ok(())
+}
+
+async fn import_archive_file(
+    pool: &SqlitePool,
+    username: &str,
+    file: &Path,
+) -> anyhow::Result<api_data::ArchiveImportReport> {
+    let user_id = users::user_by_name(pool, username)
+        .await?
+        .ok_or_else(|| anyhow!("user {} not found", username))?;
+    let data = tokio::fs::read_to_string(file).await?;
+    let archive = serde_json::from_str(&data)?;
+    Ok(word_lists::import_archive(pool, user_id, archive).await?)
 }
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    tracing_subscriber::fmt::init();
-
     let cli = Cli::parse();
 
+    let mut config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading configuration: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(database) = cli.database {
+        config.database_url = database;
+    }
+
+    match config.log_level.parse::<tracing::Level>() {
+        // RUST_LOG overrides configured level when set, e.g. to debug single module
+        Ok(level) => tracing_subscriber::fmt()
+            .with_env_filter(
+                EnvFilter::builder()
+                    .with_default_directive(LevelFilter::from_level(level).into())
+                    .from_env_lossy(),
+            )
+            .init(),
+        Err(_) => {
+            eprintln!("Invalid log level: {}", config.log_level);
+            return ExitCode::FAILURE;
+        }
+    }
+
     match cli.command {
-        Commands::Start { port } => {
-            start_server(port, create_pool(&cli.database).await).await;
-            ExitCode::SUCCESS
+        Commands::Start {
+            port,
+            migrate,
+            secure_cookies,
+        } => {
+            if let Some(port) = port {
+                config.port = port;
+            }
+            if secure_cookies {
+                config.cookie.secure = true;
+            }
+            let pool = create_pool(&config).await;
+            if migrate {
+                if let Err(e) = schema::migrate(&pool).await {
+                    eprintln!("Error applying database migrations: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            match start_server(&config, pool).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Error running server: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
         }
         Commands::User { command } => match command {
             UserCommands::Add { username, password } => {
-                let pool = create_pool(&cli.database).await;
+                let pool = create_pool(&config).await;
                 match users::add_user(&pool, &username, password).await {
                     Err(e) => {
                         eprintln!("Error creating user: {}", e);
@@ -144,7 +509,7 @@ async fn main() -> ExitCode {
                 }
             }
             UserCommands::SetPassword { username, password } => {
-                let pool = create_pool(&cli.database).await;
+                let pool = create_pool(&config).await;
                 match users::user_by_name(&pool, &username).await {
                     Ok(Some(id)) => match users::set_password(&pool, id, password).await {
                         Ok(()) => ExitCode::SUCCESS,
@@ -163,20 +528,152 @@ async fn main() -> ExitCode {
                     }
                 }
             }
+            UserCommands::Unlock { username } => {
+                let pool = create_pool(&config).await;
+                match login_throttle::clear_failures(&pool, &username).await {
+                    Ok(count) => {
+                        println!("Cleared {} failed login attempts", count);
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("Error unlocking user: {}", e);
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+        },
+        Commands::Invite { command } => match command {
+            InviteCommands::Create { uses, expires } => {
+                let pool = create_pool(&config).await;
+                let expires_at = expires
+                    .map(|days| OffsetDateTime::now_utc() + time::Duration::days(days.into()));
+                match invites::create_invite(&pool, uses, expires_at).await {
+                    Ok(code) => {
+                        println!("{}", code);
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("Error creating invite: {}", e);
+                        ExitCode::FAILURE
+                    }
+                }
+            }
         },
+        Commands::Token { command } => {
+            let pool = create_pool(&config).await;
+            match token_command(&pool, command).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Error managing tokens: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Commands::Words { command } => match command {
+            WordsCommands::Import {
+                user,
+                category,
+                format,
+                file,
+            } => {
+                let pool = create_pool(&config).await;
+                match import_words_file(&pool, &user, category, format, &file).await {
+                    Ok(report) => {
+                        println!(
+                            "Added: {}, duplicate: {}, invalid: {}",
+                            report.added, report.duplicate, report.invalid
+                        );
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("Error importing words: {}", e);
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+        },
+        Commands::Export { user, output } => {
+            let pool = create_pool(&config).await;
+            match export_archive_file(&pool, &user, output.as_deref()).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Error exporting words: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Commands::Import { user, file } => {
+            let pool = create_pool(&config).await;
+            match import_archive_file(&pool, &user, &file).await {
+                Ok(report) => {
+                    println!(
+                        "Categories: {}, added: {}, duplicate: {}, invalid: {}",
+                        report.categories,
+                        report.words.added,
+                        report.words.duplicate,
+                        report.words.invalid
+                    );
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("Error importing words: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
         Commands::Db { command } => match command {
-            DbCommands::Init => {
-                let pool = create_pool(&cli.database).await;
-                match install_schema(&pool).await {
-                    Ok(()) => ExitCode::SUCCESS,
+            DbCommands::Migrate => {
+                let pool = create_pool(&config).await;
+                match schema::migrate(&pool).await {
+                    Ok(applied) => {
+                        for migration in applied {
+                            println!("Applied {}", migration.name);
+                        }
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("Error applying database migrations: {}", e);
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+            DbCommands::Status => {
+                let pool = create_pool(&config).await;
+                match schema::status(&pool).await {
+                    Ok(statuses) => {
+                        for status in statuses {
+                            match status.applied_at {
+                                Some(applied_at) => {
+                                    println!("applied  {}  {}", status.migration.name, applied_at)
+                                }
+                                None => println!("pending  {}", status.migration.name),
+                            }
+                        }
+                        ExitCode::SUCCESS
+                    }
+                    Err(e) => {
+                        eprintln!("Error getting database migrations status: {}", e);
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+            DbCommands::Rollback { steps } => {
+                let pool = create_pool(&config).await;
+                match schema::rollback(&pool, steps).await {
+                    Ok(reverted) => {
+                        for migration in reverted {
+                            println!("Reverted {}", migration.name);
+                        }
+                        ExitCode::SUCCESS
+                    }
                     Err(e) => {
-                        eprintln!("Error initializing database schema: {}", e);
+                        eprintln!("Error reverting database migrations: {}", e);
                         ExitCode::FAILURE
                     }
                 }
             }
             DbCommands::Seed => {
-                let pool = create_pool(&cli.database).await;
+                let pool = create_pool(&config).await;
                 match seeds::install(&pool).await {
                     Ok(()) => {
                         eprintln!("Created users user and user1 with password 123");
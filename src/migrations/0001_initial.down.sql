@@ -0,0 +1,13 @@
+drop index idx_sessions_on_secret;
+drop table sessions;
+
+drop index idx_games_on_created_at;
+drop table games;
+
+drop index idx_words_on_category_id_created_at;
+drop table words;
+
+drop index idx_categories_on_user_id_created_at;
+drop table categories;
+
+drop table users;
@@ -0,0 +1,4 @@
+drop index idx_games_on_user_composed_id;
+drop index idx_games_on_user_words_id;
+
+alter table games drop column finished_at;
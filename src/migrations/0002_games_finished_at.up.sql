@@ -0,0 +1,4 @@
+alter table games add column finished_at integer;
+
+create index idx_games_on_user_words_id on games (user_words_id);
+create index idx_games_on_user_composed_id on games (user_composed_id);
@@ -0,0 +1,2 @@
+drop table composition_words;
+drop table compositions;
@@ -0,0 +1,18 @@
+create table compositions (
+       id integer not null primary key autoincrement,
+       game_id integer not null unique,
+       text text not null,
+       created_at integer not null,
+       updated_at integer not null,
+
+       foreign key(game_id) references games(id)
+);
+
+create table composition_words (
+       composition_id integer not null,
+       word_id integer not null,
+
+       primary key(composition_id, word_id),
+       foreign key(composition_id) references compositions(id),
+       foreign key(word_id) references words(id)
+);
@@ -0,0 +1,4 @@
+drop index idx_game_words_on_word_id;
+drop table game_words;
+
+alter table games drop column seed;
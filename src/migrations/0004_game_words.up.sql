@@ -0,0 +1,13 @@
+alter table games add column seed integer;
+
+create table game_words (
+       game_id integer not null,
+       word_id integer not null,
+       position integer not null,
+
+       primary key(game_id, word_id),
+       foreign key(game_id) references games(id),
+       foreign key(word_id) references words(id)
+);
+
+create index idx_game_words_on_word_id on game_words (word_id);
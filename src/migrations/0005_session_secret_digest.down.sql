@@ -0,0 +1,5 @@
+delete from sessions;
+
+drop index idx_sessions_on_secret_digest;
+alter table sessions rename column secret_digest to secret;
+create index idx_sessions_on_secret on sessions (secret);
@@ -0,0 +1,6 @@
+-- Existing sessions have plaintext secrets and can't be migrated
+delete from sessions;
+
+drop index idx_sessions_on_secret;
+alter table sessions rename column secret to secret_digest;
+create unique index idx_sessions_on_secret_digest on sessions (secret_digest);
@@ -0,0 +1,3 @@
+drop index idx_login_attempts_on_ip;
+drop index idx_login_attempts_on_username;
+drop table login_attempts;
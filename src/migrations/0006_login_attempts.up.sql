@@ -0,0 +1,9 @@
+create table login_attempts (
+       id integer not null primary key autoincrement,
+       username text not null,
+       ip text not null,
+       created_at integer not null
+);
+
+create index idx_login_attempts_on_username on login_attempts (username);
+create index idx_login_attempts_on_ip on login_attempts (ip);
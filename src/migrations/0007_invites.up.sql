@@ -0,0 +1,7 @@
+create table invites (
+       id integer not null primary key autoincrement,
+       code_digest text not null unique,
+       uses_left integer not null,
+       expires_at integer,
+       created_at integer not null
+);
@@ -0,0 +1,2 @@
+drop index idx_api_tokens_on_user_id;
+drop table api_tokens;
@@ -0,0 +1,12 @@
+create table api_tokens (
+       id integer not null primary key autoincrement,
+       user_id integer not null,
+       name text not null,
+       token_digest text not null unique,
+       scope text not null,
+       created_at integer not null,
+       last_used_at integer,
+       foreign key(user_id) references users(id)
+);
+
+create index idx_api_tokens_on_user_id on api_tokens (user_id);
@@ -0,0 +1 @@
+drop index idx_words_on_category_id_word;
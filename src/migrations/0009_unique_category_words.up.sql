@@ -0,0 +1,29 @@
+-- Words are stored in normalized form since validation was introduced, older
+-- rows are normalized by application right before this runs, except invalid
+-- ones which are only trimmed. Of duplicates within category the oldest is
+-- kept and references to the rest are moved to it.
+create temporary table duplicate_words as
+select words.id as id, kept.id as kept_id
+from words
+join (
+  select category_id, trim(word) as word, min(id) as id
+  from words group by category_id, trim(word)
+) kept on kept.category_id = words.category_id and kept.word = trim(words.word)
+where words.id != kept.id;
+
+update or ignore game_words
+set word_id = (select kept_id from duplicate_words where id = game_words.word_id)
+where word_id in (select id from duplicate_words);
+delete from game_words where word_id in (select id from duplicate_words);
+
+update or ignore composition_words
+set word_id = (select kept_id from duplicate_words where id = composition_words.word_id)
+where word_id in (select id from duplicate_words);
+delete from composition_words where word_id in (select id from duplicate_words);
+
+delete from words where id in (select id from duplicate_words);
+drop table duplicate_words;
+
+update words set word = trim(word) where word != trim(word);
+
+create unique index idx_words_on_category_id_word on words (category_id, word);
@@ -0,0 +1,3 @@
+alter table words drop column tags;
+alter table words drop column difficulty;
+alter table words drop column note;
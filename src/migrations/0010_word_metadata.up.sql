@@ -0,0 +1,4 @@
+alter table words add column note text;
+alter table words add column difficulty integer check (difficulty between 1 and 5);
+-- JSON array of strings
+alter table words add column tags text not null default '[]';
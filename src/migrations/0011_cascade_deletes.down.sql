@@ -0,0 +1,47 @@
+create table words_new (
+       id integer not null primary key autoincrement,
+       category_id integer not null,
+       word text not null,
+       created_at integer not null,
+       updated_at integer not null,
+       note text,
+       difficulty integer check (difficulty between 1 and 5),
+       -- JSON array of strings
+       tags text not null default '[]',
+
+       foreign key(category_id) references categories(id)
+);
+insert into words_new (id, category_id, word, created_at, updated_at, note, difficulty, tags)
+select id, category_id, word, created_at, updated_at, note, difficulty, tags from words;
+drop table words;
+alter table words_new rename to words;
+create index idx_words_on_category_id_created_at on words (category_id, created_at);
+create unique index idx_words_on_category_id_word on words (category_id, word);
+
+create table game_words_new (
+       game_id integer not null,
+       word_id integer not null,
+       position integer not null,
+
+       primary key(game_id, word_id),
+       foreign key(game_id) references games(id),
+       foreign key(word_id) references words(id)
+);
+insert into game_words_new (game_id, word_id, position)
+select game_id, word_id, position from game_words;
+drop table game_words;
+alter table game_words_new rename to game_words;
+create index idx_game_words_on_word_id on game_words (word_id);
+
+create table composition_words_new (
+       composition_id integer not null,
+       word_id integer not null,
+
+       primary key(composition_id, word_id),
+       foreign key(composition_id) references compositions(id),
+       foreign key(word_id) references words(id)
+);
+insert into composition_words_new (composition_id, word_id)
+select composition_id, word_id from composition_words;
+drop table composition_words;
+alter table composition_words_new rename to composition_words;
@@ -0,0 +1,4 @@
+drop trigger words_search_after_delete;
+drop trigger words_search_after_update;
+drop trigger words_search_after_insert;
+drop table words_search;
@@ -0,0 +1,26 @@
+-- Trigram tokenizer matches any substring of at least 3 characters, which
+-- covers prefixes of inflected Russian words. ё is indexed as е, since it's
+-- often written so.
+create virtual table words_search using fts5(word, note, tokenize = 'trigram');
+
+insert into words_search (rowid, word, note)
+select id, replace(replace(word, 'ё', 'е'), 'Ё', 'Е'),
+       replace(replace(coalesce(note, ''), 'ё', 'е'), 'Ё', 'Е')
+from words;
+
+create trigger words_search_after_insert after insert on words begin
+  insert into words_search (rowid, word, note)
+  values (new.id, replace(replace(new.word, 'ё', 'е'), 'Ё', 'Е'),
+          replace(replace(coalesce(new.note, ''), 'ё', 'е'), 'Ё', 'Е'));
+end;
+
+create trigger words_search_after_update after update of word, note on words begin
+  update words_search
+  set word = replace(replace(new.word, 'ё', 'е'), 'Ё', 'Е'),
+      note = replace(replace(coalesce(new.note, ''), 'ё', 'е'), 'Ё', 'Е')
+  where rowid = new.id;
+end;
+
+create trigger words_search_after_delete after delete on words begin
+  delete from words_search where rowid = old.id;
+end;
@@ -0,0 +1,4 @@
+drop index idx_categories_on_user_id_position;
+alter table categories drop column archived_at;
+alter table categories drop column pinned;
+alter table categories drop column position;
@@ -0,0 +1,12 @@
+-- User-defined order of categories, pinned ones go first
+alter table categories add column position integer not null default 0;
+alter table categories add column pinned boolean not null default false;
+-- Archived categories are hidden from listing by default
+alter table categories add column archived_at integer;
+
+update categories set position = (
+  select count(*) from categories c
+  where c.user_id = categories.user_id and c.id < categories.id
+);
+
+create index idx_categories_on_user_id_position on categories (user_id, position);
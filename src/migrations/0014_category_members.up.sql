@@ -0,0 +1,13 @@
+-- Users category is shared with, besides its owner
+create table category_members (
+       category_id integer not null,
+       user_id integer not null,
+       role text not null check (role in ('viewer', 'editor')),
+       created_at integer not null,
+
+       primary key(category_id, user_id),
+       foreign key(category_id) references categories(id) on delete cascade,
+       foreign key(user_id) references users(id) on delete cascade
+);
+
+create index idx_category_members_on_user_id on category_members (user_id);
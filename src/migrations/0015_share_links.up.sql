@@ -0,0 +1,13 @@
+-- Public read-only links to categories. Only digest of token is stored.
+create table share_links (
+       id integer not null primary key autoincrement,
+       category_id integer not null,
+       token_digest text not null unique,
+       created_at integer not null,
+       expires_at integer,
+       revoked_at integer,
+
+       foreign key(category_id) references categories(id) on delete cascade
+);
+
+create index idx_share_links_on_category_id on share_links (category_id);
@@ -0,0 +1,27 @@
+create table game_words_new (
+       game_id integer not null,
+       word_id integer not null,
+       position integer not null,
+
+       primary key(game_id, word_id),
+       foreign key(game_id) references games(id) on delete cascade,
+       foreign key(word_id) references words(id) on delete cascade
+);
+insert into game_words_new (game_id, word_id, position)
+select game_id, word_id, position from game_words;
+drop table game_words;
+alter table game_words_new rename to game_words;
+create index idx_game_words_on_word_id on game_words (word_id);
+
+create table composition_words_new (
+       composition_id integer not null,
+       word_id integer not null,
+
+       primary key(composition_id, word_id),
+       foreign key(composition_id) references compositions(id) on delete cascade,
+       foreign key(word_id) references words(id) on delete cascade
+);
+insert into composition_words_new (composition_id, word_id)
+select composition_id, word_id from composition_words;
+drop table composition_words;
+alter table composition_words_new rename to composition_words;
@@ -0,0 +1,39 @@
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use rand::{thread_rng, Rng};
+use tracing::{info_span, Instrument};
+
+/// Response header repeating id of request
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+fn new_request_id() -> String {
+    format!("{:016x}", thread_rng().gen::<u64>())
+}
+
+/// Id of request being handled, if called within `request_id` middleware
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Assigns random id to request, which is attached to log messages and
+/// returned in `REQUEST_ID_HEADER`, so that user reports can be matched
+/// with logs
+pub async fn request_id<B>(request: Request<B>, next: Next<B>) -> Response {
+    let id = new_request_id();
+    let span = info_span!("request", id = %id);
+    let mut response = REQUEST_ID
+        .scope(id.clone(), next.run(request).instrument(span))
+        .await;
+    response.headers_mut().insert(
+        REQUEST_ID_HEADER,
+        HeaderValue::from_str(&id).expect("request id is valid header value"),
+    );
+    response
+}
@@ -1,26 +1,362 @@
-use sqlx::SqlitePool;
+use futures::future::BoxFuture;
+use sqlx::sqlite::{Sqlite, SqliteArguments};
+use sqlx::{
+    query, query_as, query_scalar, types::time::OffsetDateTime, Connection, Executor,
+    SqliteConnection, SqlitePool,
+};
+use thiserror::Error;
 
-/// Installs latest application schema to SQLite database if it isn't
-/// already installed
-pub async fn install_schema(pool: &SqlitePool) -> sqlx::Result<()> {
-    let commands = include_str!("schema.sql").split(';');
-    let mut transaction = pool.begin().await?;
+use crate::validation::normalize_word;
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("SQL error: {0}")]
+    SqlError(#[from] sqlx::Error),
+    #[error("database has migration {0} which is unknown to this version of application")]
+    UnknownMigration(i64),
+    #[error("migration {0} leaves foreign key violations")]
+    ForeignKeyViolation(i64),
+}
+
+/// Data change which can't be expressed in SQL
+type DataStep = fn(&mut SqliteConnection) -> BoxFuture<'_, sqlx::Result<()>>;
+
+/// Numbered schema change with SQL for applying and reverting it
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    up: &'static str,
+    down: &'static str,
+    /// Runs in the same transaction before `up`
+    before_up: Option<DataStep>,
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        migration!($version, $name, None)
+    };
+    ($version:literal, $name:literal, $before_up:expr) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("migrations/", $name, ".up.sql")),
+            down: include_str!(concat!("migrations/", $name, ".down.sql")),
+            before_up: $before_up,
+        }
+    };
+}
+
+/// All migrations in order of application. Versions must be increasing.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_initial"),
+    migration!(2, "0002_games_finished_at"),
+    migration!(3, "0003_compositions"),
+    migration!(4, "0004_game_words"),
+    migration!(5, "0005_session_secret_digest"),
+    migration!(6, "0006_login_attempts"),
+    migration!(7, "0007_invites"),
+    migration!(8, "0008_api_tokens"),
+    migration!(
+        9,
+        "0009_unique_category_words",
+        Some(normalize_legacy_words)
+    ),
+    migration!(10, "0010_word_metadata"),
+    migration!(11, "0011_cascade_deletes"),
+    migration!(12, "0012_words_search"),
+    migration!(13, "0013_category_order"),
+    migration!(14, "0014_category_members"),
+    migration!(15, "0015_share_links"),
+    // Version 16 was withdrawn, 0004 already adds `games.seed`
+    migration!(17, "0017_restrict_used_word_deletes"),
+];
 
-    let tables_count =
-        sqlx::query_scalar!("select count(*) as c from sqlite_schema where type = 'table'")
-            .fetch_one(&mut transaction)
+/// Brings words stored before validation was introduced to the form
+/// `normalize_word` gives, so that unique index catches their duplicates.
+/// Words which aren't valid anymore are left as is.
+fn normalize_legacy_words(conn: &mut SqliteConnection) -> BoxFuture<'_, sqlx::Result<()>> {
+    Box::pin(async move {
+        let words: Vec<(i64, String)> = query_as("select id, word from words")
+            .fetch_all(&mut *conn)
             .await?;
-    if tables_count > 0 {
+        for (id, word) in words {
+            match normalize_word(&word) {
+                Ok(normalized) if normalized != word => {
+                    query("update words set word = ? where id = ?")
+                        .bind(normalized)
+                        .bind(id)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    })
+}
+
+/// State of single migration in database
+pub struct MigrationStatus {
+    pub migration: &'static Migration,
+    pub applied_at: Option<OffsetDateTime>,
+}
+
+/// Creates `schema_migrations` table if it doesn't exist yet.
+///
+/// Databases created before migrations were introduced already have initial
+/// schema installed, in this case initial migration is marked as applied.
+async fn ensure_migrations_table(pool: &SqlitePool) -> sqlx::Result<()> {
+    let mut transaction = pool.begin().await?;
+
+    let has_migrations_table = query_scalar::<_, i64>(
+        "select count(*) from sqlite_schema where type = 'table' and name = 'schema_migrations'",
+    )
+    .fetch_one(&mut transaction)
+    .await?
+        > 0;
+    if has_migrations_table {
         return Ok(());
     }
 
-    for command in commands {
-        let command_trimmed = command.trim();
-        if !command_trimmed.is_empty() {
-            sqlx::query(command).execute(&mut transaction).await?;
+    let has_legacy_schema = query_scalar::<_, i64>(
+        "select count(*) from sqlite_schema where type = 'table' and name = 'users'",
+    )
+    .fetch_one(&mut transaction)
+    .await?
+        > 0;
+
+    transaction
+        .execute(
+            "create table schema_migrations (
+               version integer not null primary key,
+               name text not null,
+               applied_at integer not null
+             )",
+        )
+        .await?;
+
+    if has_legacy_schema {
+        let initial = &MIGRATIONS[0];
+        query("insert into schema_migrations (version, name, applied_at) values (?, ?, ?)")
+            .bind(initial.version)
+            .bind(initial.name)
+            .bind(OffsetDateTime::now_utc())
+            .execute(&mut transaction)
+            .await?;
+    }
+
+    transaction.commit().await
+}
+
+/// Runs migration SQL together with bookkeeping query in single transaction.
+///
+/// Foreign keys are disabled meanwhile, so that migrations can rebuild
+/// tables referenced by other tables. They are checked before commit instead.
+async fn run_migration<'q>(
+    pool: &SqlitePool,
+    migration: &Migration,
+    before: Option<DataStep>,
+    sql: &str,
+    bookkeeping: sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>>,
+) -> Result<(), SchemaError> {
+    let mut conn = pool.acquire().await?;
+    // Has no effect inside transaction, so must be set beforehand
+    conn.execute("pragma foreign_keys = off").await?;
+
+    let result = async {
+        let mut transaction = conn.begin().await?;
+        if let Some(before) = before {
+            before(&mut transaction).await?;
+        }
+        transaction.execute(sql).await?;
+        bookkeeping.execute(&mut transaction).await?;
+        if query("pragma foreign_key_check")
+            .fetch_optional(&mut transaction)
+            .await?
+            .is_some()
+        {
+            return Err(SchemaError::ForeignKeyViolation(migration.version));
         }
+        transaction.commit().await?;
+        Ok(())
+    }
+    .await;
+
+    conn.execute("pragma foreign_keys = on").await?;
+    result
+}
+
+async fn applied_versions(pool: &SqlitePool) -> Result<Vec<(i64, OffsetDateTime)>, SchemaError> {
+    ensure_migrations_table(pool).await?;
+    let applied: Vec<(i64, OffsetDateTime)> =
+        query_as("select version, applied_at from schema_migrations order by version")
+            .fetch_all(pool)
+            .await?;
+    if let Some((version, _)) = applied
+        .iter()
+        .find(|(version, _)| !MIGRATIONS.iter().any(|m| m.version == *version))
+    {
+        return Err(SchemaError::UnknownMigration(*version));
     }
+    Ok(applied)
+}
+
+/// Lists all known migrations along with time they were applied
+pub async fn status(pool: &SqlitePool) -> Result<Vec<MigrationStatus>, SchemaError> {
+    let applied = applied_versions(pool).await?;
+    Ok(MIGRATIONS
+        .iter()
+        .map(|migration| MigrationStatus {
+            migration,
+            applied_at: applied
+                .iter()
+                .find(|(version, _)| *version == migration.version)
+                .map(|(_, applied_at)| *applied_at),
+        })
+        .collect())
+}
+
+/// Applies all pending migrations in order, each in its own transaction.
+/// Returns list of applied migrations.
+pub async fn migrate(pool: &SqlitePool) -> Result<Vec<&'static Migration>, SchemaError> {
+    let applied = applied_versions(pool).await?;
+    let mut newly_applied = Vec::new();
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| !applied.iter().any(|(version, _)| *version == m.version))
+    {
+        let bookkeeping =
+            query("insert into schema_migrations (version, name, applied_at) values (?, ?, ?)")
+                .bind(migration.version)
+                .bind(migration.name)
+                .bind(OffsetDateTime::now_utc());
+        run_migration(
+            pool,
+            migration,
+            migration.before_up,
+            migration.up,
+            bookkeeping,
+        )
+        .await?;
+        newly_applied.push(migration);
+    }
+
+    Ok(newly_applied)
+}
 
-    transaction.commit().await?;
-    Ok(())
+/// Reverts up to `steps` latest applied migrations, latest first.
+/// Returns list of reverted migrations.
+pub async fn rollback(
+    pool: &SqlitePool,
+    steps: usize,
+) -> Result<Vec<&'static Migration>, SchemaError> {
+    let applied = applied_versions(pool).await?;
+    let mut reverted = Vec::new();
+
+    for (version, _) in applied.iter().rev().take(steps) {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.version == *version)
+            .expect("applied migration is known");
+        let bookkeeping =
+            query("delete from schema_migrations where version = ?").bind(migration.version);
+        run_migration(pool, migration, None, migration.down, bookkeeping).await?;
+        reverted.push(migration);
+    }
+
+    Ok(reverted)
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::{query_as, query_scalar, Executor, SqlitePool};
+
+    use super::{migrate, rollback, status, MIGRATIONS};
+
+    async fn tables_count(pool: &SqlitePool) -> i64 {
+        query_scalar(
+            "select count(*) from sqlite_schema
+             where type = 'table' and name not in ('schema_migrations', 'sqlite_sequence')",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_migrate_applies_all() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+
+        let applied = migrate(&pool).await.expect("migrate");
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert!(status(&pool)
+            .await
+            .unwrap()
+            .iter()
+            .all(|s| s.applied_at.is_some()));
+
+        let applied_again = migrate(&pool).await.expect("migrate again");
+        assert!(applied_again.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rollback_all_and_migrate_again() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        migrate(&pool).await.unwrap();
+
+        let reverted = rollback(&pool, MIGRATIONS.len()).await.expect("rollback");
+        assert_eq!(reverted.len(), MIGRATIONS.len());
+        assert_eq!(tables_count(&pool).await, 0);
+        assert!(status(&pool)
+            .await
+            .unwrap()
+            .iter()
+            .all(|s| s.applied_at.is_none()));
+
+        migrate(&pool).await.expect("migrate after rollback");
+        assert!(tables_count(&pool).await > 0);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_schema_marked_as_initial() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        pool.execute(MIGRATIONS[0].up).await.unwrap();
+
+        migrate(&pool).await.expect("migrate legacy database");
+        let statuses = status(&pool).await.unwrap();
+        assert!(statuses.iter().all(|s| s.applied_at.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_unique_words_migration_normalizes_legacy_words() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        migrate(&pool).await.unwrap();
+        rollback(&pool, MIGRATIONS.len() - 8).await.unwrap();
+
+        pool.execute(
+            "insert into users (id, name, created_at, updated_at) values (1, 'user', 0, 0);
+             insert into categories (id, user_id, name, created_at, updated_at)
+             values (1, 1, 'birds', 0, 0);
+             insert into words (id, category_id, word, created_at, updated_at)
+             values (1, 1, 'снегирь', 0, 0), (2, 1, ' Снегирь', 0, 0),
+               (3, 1, 'и\u{0306}волга', 0, 0), (4, 1, 'рыба1', 0, 0)",
+        )
+        .await
+        .unwrap();
+
+        migrate(&pool).await.expect("migrate");
+        let words: Vec<(i64, String)> = query_as("select id, word from words order by id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            words,
+            vec![
+                (1, "снегирь".to_owned()),
+                (3, "йволга".to_owned()),
+                (4, "рыба1".to_owned())
+            ]
+        );
+    }
 }
@@ -5,8 +5,8 @@ use sqlx::{query, types::time::OffsetDateTime, SqlitePool};
 use crate::users::add_user;
 
 pub async fn install(pool: &SqlitePool) -> Result<()> {
-    let user = add_user(&pool, &"user", "123".to_string()).await?;
-    add_user(&pool, &"user1", "123".to_string()).await?;
+    let user = add_user(pool, "user", "123".to_string()).await?;
+    add_user(pool, "user1", "123".to_string()).await?;
     let current_time = OffsetDateTime::now_utc();
 
     let mut category_ids: Vec<i64> = Vec::new();
@@ -0,0 +1,172 @@
+use base64::Engine;
+use rand::{thread_rng, RngCore};
+use sqlx::{query, query_as, query_scalar, types::time::OffsetDateTime, SqlitePool};
+use time::UtcOffset;
+
+use crate::api_data::{CreatedShareLink, ShareLink};
+use crate::users::secret_digest;
+
+fn new_share_token() -> String {
+    let mut token = [0u8; 24];
+    thread_rng().fill_bytes(&mut token);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(token)
+}
+
+/// Path of public page showing category shared with token
+pub fn share_path(token: &str) -> String {
+    format!("/s/{}", token)
+}
+
+pub async fn create_share_link(
+    pool: &SqlitePool,
+    category_id: i64,
+    expires_at: Option<OffsetDateTime>,
+) -> sqlx::Result<CreatedShareLink> {
+    let created_at = OffsetDateTime::now_utc();
+    // Stored as text with offset, so keep all times in UTC
+    let expires_at = expires_at.map(|t| t.to_offset(UtcOffset::UTC));
+    let token = new_share_token();
+    let token_digest = secret_digest(&token);
+    let id = query!(
+        "insert into share_links (category_id, token_digest, created_at, expires_at) values (?, ?, ?, ?)",
+        category_id,
+        token_digest,
+        created_at,
+        expires_at
+    )
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    Ok(CreatedShareLink {
+        link: ShareLink {
+            id,
+            category_id,
+            created_at,
+            expires_at,
+            revoked_at: None,
+        },
+        path: share_path(&token),
+        token,
+    })
+}
+
+pub async fn list_share_links(pool: &SqlitePool, category_id: i64) -> sqlx::Result<Vec<ShareLink>> {
+    query_as!(
+        ShareLink,
+        r#"select id as "id!", category_id, created_at as "created_at: OffsetDateTime",
+          expires_at as "expires_at: OffsetDateTime", revoked_at as "revoked_at: OffsetDateTime"
+        from share_links where category_id = ? order by id"#,
+        category_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Disables link, returning false if category has no such active link
+pub async fn revoke_share_link(
+    pool: &SqlitePool,
+    category_id: i64,
+    link_id: i64,
+) -> sqlx::Result<bool> {
+    let now = OffsetDateTime::now_utc();
+    let rows_affected = query!(
+        "update share_links set revoked_at = ?
+        where id = ? and category_id = ? and revoked_at is null",
+        now,
+        link_id,
+        category_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected == 1)
+}
+
+/// Looks up category shared with token, unless link is revoked or expired
+pub async fn shared_category_id(pool: &SqlitePool, token: &str) -> sqlx::Result<Option<i64>> {
+    let token_digest = secret_digest(token);
+    let now = OffsetDateTime::now_utc();
+    query_scalar!(
+        "select category_id from share_links
+        where token_digest = ? and revoked_at is null
+          and (expires_at is null or julianday(expires_at) > julianday(?))",
+        token_digest,
+        now
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::types::time::OffsetDateTime;
+    use time::{Duration, UtcOffset};
+
+    use super::{create_share_link, list_share_links, revoke_share_link, shared_category_id};
+    use crate::test_utils::*;
+
+    #[tokio::test]
+    async fn test_share_link_lifecycle() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        let other_category = add_test_category(&pool, user).await;
+
+        let created = create_share_link(&pool, category, None).await.unwrap();
+        assert_eq!(
+            shared_category_id(&pool, &created.token).await.unwrap(),
+            Some(category)
+        );
+
+        assert!(!revoke_share_link(&pool, other_category, created.link.id)
+            .await
+            .unwrap());
+        assert!(revoke_share_link(&pool, category, created.link.id)
+            .await
+            .unwrap());
+        assert!(shared_category_id(&pool, &created.token)
+            .await
+            .unwrap()
+            .is_none());
+        let links = list_share_links(&pool, category).await.unwrap();
+        assert!(links[0].revoked_at.is_some());
+
+        let expired = create_share_link(
+            &pool,
+            category,
+            Some(OffsetDateTime::now_utc() - Duration::minutes(1)),
+        )
+        .await
+        .unwrap();
+        assert!(shared_category_id(&pool, &expired.token)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Offset east of UTC makes local time text sort after current UTC time
+        let eastern = UtcOffset::from_hms(3, 0, 0).unwrap();
+        let expired = create_share_link(
+            &pool,
+            category,
+            Some((OffsetDateTime::now_utc() - Duration::minutes(1)).to_offset(eastern)),
+        )
+        .await
+        .unwrap();
+        assert!(shared_category_id(&pool, &expired.token)
+            .await
+            .unwrap()
+            .is_none());
+        let active = create_share_link(
+            &pool,
+            category,
+            Some((OffsetDateTime::now_utc() + Duration::minutes(1)).to_offset(eastern)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            shared_category_id(&pool, &active.token).await.unwrap(),
+            Some(category)
+        );
+    }
+}
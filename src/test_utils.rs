@@ -1,12 +1,14 @@
 use sqlx::{query, SqlitePool};
 
-use crate::schema::install_schema;
+use crate::db::connect_options;
+use crate::schema::migrate;
 
 pub async fn test_database_pool() -> SqlitePool {
-    let pool = SqlitePool::connect(":memory:")
+    let options = connect_options(":memory:").expect("in-memory database url");
+    let pool = SqlitePool::connect_with(options)
         .await
         .expect("create pool with in-memory sqlite database");
-    install_schema(&pool).await.expect("install schema");
+    migrate(&pool).await.expect("apply migrations");
     pool
 }
 
@@ -22,7 +24,7 @@ pub async fn add_test_user(pool: &SqlitePool, name: &str) -> i64 {
 }
 
 pub async fn add_test_category(pool: &SqlitePool, user_id: i64) -> i64 {
-    query!("insert into categories(user_id, name, created_at, updated_at) values (?, null, '2022-01-01', '2022-01-01')", user_id)
+    query!("insert into categories(user_id, name, created_at, updated_at) values (?, null, '2022-01-01T00:00:00Z', '2022-01-01T00:00:00Z')", user_id)
         .execute(pool)
         .await
         .expect("add test category")
@@ -30,9 +32,32 @@ pub async fn add_test_category(pool: &SqlitePool, user_id: i64) -> i64 {
 }
 
 pub async fn add_test_word(pool: &SqlitePool, category_id: i64) -> i64 {
-    query!("insert into words(category_id, word, created_at, updated_at) values (?, 'word'+hex(randomblob(8)), '2022-01-01', '2022-01-01')", category_id)
+    query!("insert into words(category_id, word, created_at, updated_at) values (?, 'word' || hex(randomblob(8)), '2022-01-01T00:00:00Z', '2022-01-01T00:00:00Z')", category_id)
         .execute(pool)
         .await
         .expect("add test word")
         .last_insert_rowid()
 }
+
+pub async fn add_test_named_word(pool: &SqlitePool, category_id: i64, word: &str) -> i64 {
+    query!("insert into words(category_id, word, created_at, updated_at) values (?, ?, '2022-01-01T00:00:00Z', '2022-01-01T00:00:00Z')", category_id, word)
+        .execute(pool)
+        .await
+        .expect("add test word")
+        .last_insert_rowid()
+}
+
+pub async fn add_test_game(pool: &SqlitePool, user_words_id: i64, user_composed_id: i64) -> i64 {
+    query!("insert into games(user_words_id, user_composed_id, created_at) values (?, ?, '2022-01-01T00:00:00Z')", user_words_id, user_composed_id)
+        .execute(pool)
+        .await
+        .expect("add test game")
+        .last_insert_rowid()
+}
+
+pub async fn add_test_game_word(pool: &SqlitePool, game_id: i64, word_id: i64) {
+    query!("insert into game_words(game_id, word_id, position) values (?, ?, (select count(*) from game_words where game_id = ?))", game_id, word_id, game_id)
+        .execute(pool)
+        .await
+        .expect("add test game word");
+}
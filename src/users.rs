@@ -2,11 +2,26 @@ use argon2::{Argon2, PasswordHash};
 use base64::Engine;
 use password_hash::SaltString;
 use rand::{thread_rng, RngCore};
-use sqlx::{query, query_scalar, types::time::OffsetDateTime, SqlitePool};
+use sha2::{Digest, Sha256};
+use sqlx::{
+    query, query_as, query_scalar, types::time::OffsetDateTime, SqliteConnection, SqlitePool,
+};
 use thiserror::Error;
+use time::Duration;
 use tokio::task::spawn_blocking;
 use tracing::warn;
 
+use crate::api_data::SessionInfo;
+use crate::db::is_unique_violation;
+use crate::invites::consume_invite;
+
+/// Session expires when it isn't used for this long
+pub const SESSION_IDLE_TIMEOUT: Duration = Duration::days(30);
+/// Session expires this long after login regardless of use
+pub const SESSION_ABSOLUTE_TIMEOUT: Duration = Duration::days(180);
+/// `last_used_at` is updated not more often than this to avoid writing on every request
+const SESSION_TOUCH_INTERVAL: Duration = Duration::minutes(1);
+
 #[derive(Error, Debug)]
 pub enum UsersError {
     #[error("password hashing error")]
@@ -15,6 +30,19 @@ pub enum UsersError {
     SqlError(#[from] sqlx::Error),
     #[error("user with id={0} not found")]
     NoSuchUser(i64),
+    #[error("username {0:?} is already taken")]
+    UsernameTaken(String),
+    #[error("invite code is invalid, used up or expired")]
+    InvalidInvite,
+}
+
+/// Maps unique constraint violation on `users.name` to `UsernameTaken`
+fn insert_user_error(error: sqlx::Error, username: &str) -> UsersError {
+    if is_unique_violation(&error) {
+        UsersError::UsernameTaken(username.to_owned())
+    } else {
+        UsersError::SqlError(error)
+    }
 }
 
 fn check_password_hash(password_hash: &str, password: &str) -> bool {
@@ -68,9 +96,15 @@ pub async fn authenticate_user_by_password(
 }
 
 fn new_session_secret() -> String {
-    let mut secret = [0u8; 12];
+    let mut secret = [0u8; 32];
     thread_rng().fill_bytes(&mut secret);
-    base64::engine::general_purpose::URL_SAFE.encode(&secret)
+    base64::engine::general_purpose::URL_SAFE.encode(secret)
+}
+
+/// Only digests of session secrets and invite codes are stored, so that
+/// leaked database doesn't give access to live sessions
+pub fn secret_digest(secret: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE.encode(Sha256::digest(secret.as_bytes()))
 }
 
 pub async fn create_session(
@@ -80,15 +114,119 @@ pub async fn create_session(
 ) -> sqlx::Result<String> {
     let time = OffsetDateTime::now_utc();
     let secret = new_session_secret();
-    query!("insert into sessions (user_id, secret, created_user_agent, created_at, last_used_at) values (?, ?, ?, ?, ?)",
-           user_id, secret, user_agent, time, time).execute(pool).await?;
+    let secret_digest = secret_digest(&secret);
+    query!("insert into sessions (user_id, secret_digest, created_user_agent, created_at, last_used_at) values (?, ?, ?, ?, ?)",
+           user_id, secret_digest, user_agent, time, time).execute(pool).await?;
     Ok(secret)
 }
 
-pub async fn get_session_user(pool: &SqlitePool, secret: &str) -> sqlx::Result<Option<i64>> {
-    query_scalar!("select user_id from sessions where secret = ?", secret)
-        .fetch_optional(pool)
-        .await
+/// Authenticated session
+pub struct Session {
+    pub id: i64,
+    pub user_id: i64,
+}
+
+fn session_expired(
+    created_at: OffsetDateTime,
+    last_used_at: OffsetDateTime,
+    now: OffsetDateTime,
+) -> bool {
+    now - last_used_at > SESSION_IDLE_TIMEOUT || now - created_at > SESSION_ABSOLUTE_TIMEOUT
+}
+
+/// Looks up session by secret. Expired sessions are deleted and not returned.
+pub async fn get_session_user(pool: &SqlitePool, secret: &str) -> sqlx::Result<Option<Session>> {
+    let secret_digest = secret_digest(secret);
+    let record = query!(
+        r#"select id as "id!", user_id, created_at as "created_at: OffsetDateTime",
+          last_used_at as "last_used_at: OffsetDateTime"
+        from sessions where secret_digest = ?"#,
+        secret_digest
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let record = match record {
+        Some(record) => record,
+        None => return Ok(None),
+    };
+
+    let now = OffsetDateTime::now_utc();
+    if session_expired(record.created_at, record.last_used_at, now) {
+        query!("delete from sessions where id = ?", record.id)
+            .execute(pool)
+            .await?;
+        return Ok(None);
+    }
+    if now - record.last_used_at > SESSION_TOUCH_INTERVAL {
+        query!(
+            "update sessions set last_used_at = ? where id = ?",
+            now,
+            record.id
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(Some(Session {
+        id: record.id,
+        user_id: record.user_id,
+    }))
+}
+
+pub async fn delete_session_by_secret(pool: &SqlitePool, secret: &str) -> sqlx::Result<()> {
+    let secret_digest = secret_digest(secret);
+    query!(
+        "delete from sessions where secret_digest = ?",
+        secret_digest
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Deletes user's session, returning false if user has no such session
+pub async fn delete_session(
+    pool: &SqlitePool,
+    user_id: i64,
+    session_id: i64,
+) -> sqlx::Result<bool> {
+    let rows_affected = query!(
+        "delete from sessions where id = ? and user_id = ?",
+        session_id,
+        user_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(rows_affected == 1)
+}
+
+/// Lists user's sessions which aren't expired, most recently used first
+pub async fn list_sessions(
+    pool: &SqlitePool,
+    user_id: i64,
+    current_session_id: i64,
+) -> sqlx::Result<Vec<SessionInfo>> {
+    let now = OffsetDateTime::now_utc();
+    let sessions = query_as!(
+        SessionInfo,
+        r#"select id as "id!", created_user_agent,
+          created_at as "created_at: OffsetDateTime",
+          last_used_at as "last_used_at: OffsetDateTime",
+          id = ? as "current!: bool"
+        from sessions where user_id = ?
+        order by last_used_at desc"#,
+        current_session_id,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions
+        .into_iter()
+        .filter(|s| !session_expired(s.created_at, s.last_used_at, now))
+        .collect())
 }
 
 fn password_hash(password: String) -> password_hash::errors::Result<String> {
@@ -104,10 +242,19 @@ pub async fn add_user(
     username: &str,
     password: String,
 ) -> Result<i64, UsersError> {
-    let time = OffsetDateTime::now_utc();
     let hash = spawn_blocking(|| password_hash(password))
         .await
         .expect("Spawn blocking")?;
+    let mut conn = pool.acquire().await?;
+    insert_user(&mut conn, username, &hash).await
+}
+
+async fn insert_user(
+    conn: &mut SqliteConnection,
+    username: &str,
+    hash: &str,
+) -> Result<i64, UsersError> {
+    let time = OffsetDateTime::now_utc();
     let user_id = query!(
         "insert into users (name, password, created_at, updated_at) values (?, ?, ?, ?)",
         username,
@@ -115,12 +262,34 @@ pub async fn add_user(
         time,
         time
     )
-    .execute(pool)
-    .await?
+    .execute(conn)
+    .await
+    .map_err(|e| insert_user_error(e, username))?
     .last_insert_rowid();
     Ok(user_id)
 }
 
+/// Adds user using invite code. Invite isn't used up if username is taken.
+/// Password is hashed only after invite is accepted, so that requests with
+/// bogus invite codes are cheap.
+pub async fn register_user(
+    pool: &SqlitePool,
+    invite_code: &str,
+    username: &str,
+    password: String,
+) -> Result<i64, UsersError> {
+    let mut transaction = pool.begin().await?;
+    if !consume_invite(&mut transaction, invite_code).await? {
+        return Err(UsersError::InvalidInvite);
+    }
+    let hash = spawn_blocking(|| password_hash(password))
+        .await
+        .expect("Spawn blocking")?;
+    let user_id = insert_user(&mut transaction, username, &hash).await?;
+    transaction.commit().await?;
+    Ok(user_id)
+}
+
 pub async fn set_password(pool: &SqlitePool, id: i64, password: String) -> Result<(), UsersError> {
     let hash = spawn_blocking(|| password_hash(password))
         .await
@@ -145,3 +314,93 @@ pub async fn user_by_name(pool: &SqlitePool, username: &str) -> Result<Option<i6
             .await?,
     )
 }
+
+#[cfg(test)]
+mod test {
+    use sqlx::{query, query_scalar, types::time::OffsetDateTime};
+
+    use super::{
+        create_session, get_session_user, register_user, UsersError, SESSION_IDLE_TIMEOUT,
+    };
+    use crate::invites::create_invite;
+    use crate::test_utils::*;
+
+    #[tokio::test]
+    async fn test_get_session_user_bumps_last_used_at() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let secret = create_session(&pool, user, "test").await.unwrap();
+        let old_time = OffsetDateTime::now_utc() - SESSION_IDLE_TIMEOUT / 2;
+        query!("update sessions set last_used_at = ?", old_time)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let session = get_session_user(&pool, &secret).await.unwrap().unwrap();
+        assert_eq!(session.user_id, user);
+
+        let last_used_at =
+            query_scalar!(r#"select last_used_at as "last_used_at: OffsetDateTime" from sessions"#)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(last_used_at > old_time);
+    }
+
+    #[tokio::test]
+    async fn test_get_session_user_idle_expired() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let secret = create_session(&pool, user, "test").await.unwrap();
+        let old_time = OffsetDateTime::now_utc() - SESSION_IDLE_TIMEOUT * 2;
+        query!("update sessions set last_used_at = ?", old_time)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(get_session_user(&pool, &secret).await.unwrap().is_none());
+        let sessions_count = query_scalar!("select count(*) from sessions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(sessions_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_secret_not_stored() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let secret = create_session(&pool, user, "test").await.unwrap();
+
+        let stored = query_scalar!("select secret_digest from sessions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_ne!(stored, secret);
+        assert!(get_session_user(&pool, &secret).await.unwrap().is_some());
+        assert!(get_session_user(&pool, &stored).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_register_user() {
+        let pool = test_database_pool().await;
+        add_test_user(&pool, "taken").await;
+        let code = create_invite(&pool, 1, None).await.unwrap();
+
+        assert!(matches!(
+            register_user(&pool, "wrong", "user", "password".to_owned()).await,
+            Err(UsersError::InvalidInvite)
+        ));
+        assert!(matches!(
+            register_user(&pool, &code, "taken", "password".to_owned()).await,
+            Err(UsersError::UsernameTaken(_))
+        ));
+        register_user(&pool, &code, "user", "password".to_owned())
+            .await
+            .unwrap();
+        assert!(matches!(
+            register_user(&pool, &code, "user2", "password".to_owned()).await,
+            Err(UsersError::InvalidInvite)
+        ));
+    }
+}
@@ -0,0 +1,284 @@
+use serde::Serialize;
+use serde_json::json;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::controller::error::ApiError;
+
+/// Maximum length of word in characters
+pub const MAX_WORD_LENGTH: usize = 100;
+/// Maximum length of category name in characters
+pub const MAX_CATEGORY_NAME_LENGTH: usize = 100;
+/// Maximum length of word note in characters
+pub const MAX_NOTE_LENGTH: usize = 1000;
+/// Maximum length of composition text in characters
+pub const MAX_COMPOSITION_TEXT_LENGTH: usize = 10000;
+pub const MAX_TAG_LENGTH: usize = 30;
+pub const MAX_TAGS_COUNT: usize = 10;
+pub const MIN_DIFFICULTY: u8 = 1;
+pub const MAX_DIFFICULTY: u8 = 5;
+
+/// Problem with one field of request
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: &'static str,
+    /// Machine-readable reason, like `too_long`
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Field errors collected while validating request
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        FieldErrors::default()
+    }
+
+    pub fn add(&mut self, field: &'static str, code: &'static str, message: String) {
+        self.0.push(FieldError {
+            field,
+            code,
+            message,
+        });
+    }
+
+    /// Records error of `result` if any, returning normalized value otherwise
+    pub fn check<T>(&mut self, field: &'static str, result: Result<T, ValueError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.add(field, error.code(), error.message());
+                None
+            }
+        }
+    }
+
+    /// Fails with list of fields if any error was recorded
+    pub fn into_result(self) -> Result<(), ApiError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::Validation(
+                "Invalid request fields".to_owned(),
+                Some(json!({ "fields": self.0 })),
+            ))
+        }
+    }
+}
+
+/// Validates request with single field
+pub fn validate_field<T>(
+    field: &'static str,
+    result: Result<T, ValueError>,
+) -> Result<T, ApiError> {
+    let mut errors = FieldErrors::new();
+    let value = errors.check(field, result);
+    errors.into_result()?;
+    Ok(value.expect("no errors recorded"))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ValueError {
+    Empty,
+    TooLong(usize),
+    InvalidCharacter(char),
+    OutOfRange(u8, u8),
+    TooMany(usize),
+}
+
+impl ValueError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValueError::Empty => "empty",
+            ValueError::TooLong(_) => "too_long",
+            ValueError::InvalidCharacter(_) => "invalid_character",
+            ValueError::OutOfRange(..) => "out_of_range",
+            ValueError::TooMany(_) => "too_many",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ValueError::Empty => "Must not be empty".to_owned(),
+            ValueError::TooLong(max) => format!("Must be at most {} characters long", max),
+            ValueError::InvalidCharacter(c) => format!(
+                "Character {:?} is not allowed, only Cyrillic and Latin letters, hyphen and space are",
+                c
+            ),
+            ValueError::OutOfRange(min, max) => format!("Must be from {} to {}", min, max),
+            ValueError::TooMany(max) => format!("Must have at most {} items", max),
+        }
+    }
+}
+
+/// How text field is normalized and checked
+pub struct TextRules {
+    pub max_length: usize,
+    pub lowercase: bool,
+}
+
+pub const WORD_RULES: TextRules = TextRules {
+    max_length: MAX_WORD_LENGTH,
+    lowercase: true,
+};
+
+pub const CATEGORY_NAME_RULES: TextRules = TextRules {
+    max_length: MAX_CATEGORY_NAME_LENGTH,
+    lowercase: false,
+};
+
+pub const TAG_RULES: TextRules = TextRules {
+    max_length: MAX_TAG_LENGTH,
+    lowercase: true,
+};
+
+fn is_allowed_letter(c: char) -> bool {
+    c.is_alphabetic()
+        && matches!(c,
+            'A'..='Z' | 'a'..='z'
+            // Latin-1 Supplement and Latin Extended-A/B
+            | '\u{00C0}'..='\u{024F}'
+            // Cyrillic and Cyrillic Supplement
+            | '\u{0400}'..='\u{052F}')
+}
+
+/// Brings text to canonical form: NFC, trimmed, inner whitespace collapsed to
+/// single spaces, optionally lowercased. Then checks it against rules.
+pub fn normalize_text(text: &str, rules: &TextRules) -> Result<String, ValueError> {
+    let normalized: String = text.nfc().collect();
+    let mut normalized = normalized.split_whitespace().collect::<Vec<_>>().join(" ");
+    if rules.lowercase {
+        normalized = normalized.to_lowercase();
+    }
+
+    if normalized.is_empty() {
+        return Err(ValueError::Empty);
+    }
+    if normalized.chars().count() > rules.max_length {
+        return Err(ValueError::TooLong(rules.max_length));
+    }
+    if let Some(c) = normalized
+        .chars()
+        .find(|&c| !(is_allowed_letter(c) || c == '-' || c == ' '))
+    {
+        return Err(ValueError::InvalidCharacter(c));
+    }
+    Ok(normalized)
+}
+
+pub fn normalize_word(word: &str) -> Result<String, ValueError> {
+    normalize_text(word, &WORD_RULES)
+}
+
+pub fn normalize_category_name(name: &str) -> Result<String, ValueError> {
+    normalize_text(name, &CATEGORY_NAME_RULES)
+}
+
+/// Notes are free text, only trimmed; empty note is no note
+pub fn normalize_note(note: &str) -> Result<Option<String>, ValueError> {
+    let note: String = note.trim().nfc().collect();
+    if note.is_empty() {
+        Ok(None)
+    } else if note.chars().count() > MAX_NOTE_LENGTH {
+        Err(ValueError::TooLong(MAX_NOTE_LENGTH))
+    } else {
+        Ok(Some(note))
+    }
+}
+
+/// Composition text is kept as written, it only must not be blank
+pub fn check_composition_text(text: &str) -> Result<&str, ValueError> {
+    if text.trim().is_empty() {
+        Err(ValueError::Empty)
+    } else if text.chars().count() > MAX_COMPOSITION_TEXT_LENGTH {
+        Err(ValueError::TooLong(MAX_COMPOSITION_TEXT_LENGTH))
+    } else {
+        Ok(text)
+    }
+}
+
+pub fn check_difficulty(difficulty: u8) -> Result<u8, ValueError> {
+    if (MIN_DIFFICULTY..=MAX_DIFFICULTY).contains(&difficulty) {
+        Ok(difficulty)
+    } else {
+        Err(ValueError::OutOfRange(MIN_DIFFICULTY, MAX_DIFFICULTY))
+    }
+}
+
+/// Normalizes each tag and drops repeated ones, keeping order
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, ValueError> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = normalize_text(tag, &TAG_RULES)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS_COUNT {
+        return Err(ValueError::TooMany(MAX_TAGS_COUNT));
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        check_difficulty, normalize_category_name, normalize_note, normalize_tags, normalize_word,
+        FieldErrors, ValueError,
+    };
+
+    #[test]
+    fn test_normalize_word() {
+        assert_eq!(normalize_word("  Снегирь ").unwrap(), "снегирь");
+        assert_eq!(normalize_word("иван-да-марья").unwrap(), "иван-да-марья");
+        assert_eq!(normalize_word("Café").unwrap(), "café");
+        // Decomposed "й" is composed
+        assert_eq!(normalize_word("и\u{0306}од").unwrap(), "йод");
+        assert_eq!(normalize_word("   "), Err(ValueError::Empty));
+        assert_eq!(
+            normalize_word(&"а".repeat(101)),
+            Err(ValueError::TooLong(100))
+        );
+        assert_eq!(
+            normalize_word("рыба1"),
+            Err(ValueError::InvalidCharacter('1'))
+        );
+        assert_eq!(
+            normalize_word("λόγος"),
+            Err(ValueError::InvalidCharacter('λ'))
+        );
+    }
+
+    #[test]
+    fn test_normalize_category_name() {
+        assert_eq!(
+            normalize_category_name(" Зимние   Птицы ").unwrap(),
+            "Зимние Птицы"
+        );
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let mut errors = FieldErrors::new();
+        assert_eq!(
+            errors.check("word", normalize_word("кот")),
+            Some("кот".to_owned())
+        );
+        assert!(errors.check("word", normalize_word("")).is_none());
+        errors.into_result().unwrap_err();
+    }
+
+    #[test]
+    fn test_word_metadata() {
+        assert_eq!(normalize_note("  ").unwrap(), None);
+        assert_eq!(normalize_note(" птица ").unwrap(), Some("птица".to_owned()));
+        check_difficulty(0).unwrap_err();
+        check_difficulty(6).unwrap_err();
+        assert_eq!(
+            normalize_tags(&["Птицы".to_owned(), "птицы".to_owned(), "зима".to_owned()]).unwrap(),
+            vec!["птицы", "зима"]
+        );
+        normalize_tags(&["тег1".to_owned()]).unwrap_err();
+    }
+}
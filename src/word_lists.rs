@@ -0,0 +1,403 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use sqlx::types::{time::OffsetDateTime, Json as SqlJson};
+use sqlx::{query, query_scalar, SqliteConnection, SqlitePool};
+use thiserror::Error;
+
+use crate::api_data::{
+    ArchiveCategory, ArchiveImportReport, ArchiveWord, WordImportReport, WordsArchive,
+};
+use crate::validation::{check_difficulty, normalize_note, normalize_tags, normalize_word};
+
+/// Version of archive format produced by `export_archive`. Version 1 had
+/// bare words without metadata.
+pub const ARCHIVE_VERSION: u32 = 2;
+
+#[derive(Error, Debug)]
+pub enum WordListError {
+    #[error("can't parse word list: {0}")]
+    ParseError(String),
+    #[error("unsupported archive version {0}")]
+    UnsupportedArchiveVersion(u32),
+    #[error("SQL error")]
+    SqlError(#[from] sqlx::Error),
+}
+
+/// Format of word list file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WordListFormat {
+    /// One word per line
+    #[serde(alias = "txt")]
+    #[value(alias = "txt")]
+    Text,
+    /// Word in first column, optional `word` header row
+    Csv,
+    /// Array of strings
+    Json,
+}
+
+impl WordListFormat {
+    pub fn from_content_type(content_type: &str) -> Option<WordListFormat> {
+        match content_type.split(';').next().unwrap_or("").trim() {
+            "text/plain" => Some(WordListFormat::Text),
+            "text/csv" => Some(WordListFormat::Csv),
+            "application/json" => Some(WordListFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            WordListFormat::Text => "text/plain; charset=utf-8",
+            WordListFormat::Csv => "text/csv; charset=utf-8",
+            WordListFormat::Json => "application/json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            WordListFormat::Text => "txt",
+            WordListFormat::Csv => "csv",
+            WordListFormat::Json => "json",
+        }
+    }
+
+    /// Guesses format by file extension, defaulting to plain text
+    pub fn from_path(path: &Path) -> WordListFormat {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => WordListFormat::Csv,
+            Some("json") => WordListFormat::Json,
+            _ => WordListFormat::Text,
+        }
+    }
+}
+
+/// Splits word list into raw entries, without any validation
+pub fn parse_word_list(format: WordListFormat, data: &str) -> Result<Vec<String>, WordListError> {
+    match format {
+        WordListFormat::Text => Ok(data.lines().map(|line| line.to_owned()).collect()),
+        WordListFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(data.as_bytes());
+            let mut entries = Vec::new();
+            for (i, record) in reader.records().enumerate() {
+                let record = record.map_err(|e| WordListError::ParseError(e.to_string()))?;
+                let field = record.get(0).unwrap_or("");
+                if i == 0 && field.trim() == "word" {
+                    continue;
+                }
+                entries.push(field.to_owned());
+            }
+            Ok(entries)
+        }
+        WordListFormat::Json => {
+            serde_json::from_str(data).map_err(|e| WordListError::ParseError(e.to_string()))
+        }
+    }
+}
+
+/// Formats words so that `parse_word_list` gives the same words back
+pub fn format_word_list(format: WordListFormat, words: &[String]) -> String {
+    match format {
+        WordListFormat::Text => words.iter().map(|w| format!("{}\n", w)).collect(),
+        WordListFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(["word"]).expect("write csv header");
+            for word in words {
+                writer.write_record([word]).expect("write csv record");
+            }
+            String::from_utf8(writer.into_inner().expect("flush csv")).expect("csv is utf-8")
+        }
+        WordListFormat::Json => serde_json::to_string(words).expect("serialize words"),
+    }
+}
+
+/// Adds entries to category in single transaction, skipping invalid entries
+/// and words already present in category. Entries are normalized the same
+/// way as words added one by one. Doesn't check category ownership.
+pub async fn import_words(
+    pool: &SqlitePool,
+    category_id: i64,
+    entries: Vec<String>,
+) -> Result<WordImportReport, WordListError> {
+    let mut transaction = pool.begin().await?;
+    let entries = entries.into_iter().map(ArchiveWord::from).collect();
+    let report = insert_words(&mut transaction, category_id, entries).await?;
+    transaction.commit().await?;
+    Ok(report)
+}
+
+/// Word with metadata in normalized form, `None` if any part is invalid
+fn normalize_archive_word(entry: &ArchiveWord) -> Option<ArchiveWord> {
+    Some(ArchiveWord {
+        word: normalize_word(&entry.word).ok()?,
+        note: match &entry.note {
+            Some(note) => normalize_note(note).ok()?,
+            None => None,
+        },
+        difficulty: match entry.difficulty {
+            Some(difficulty) => Some(check_difficulty(difficulty).ok()?),
+            None => None,
+        },
+        tags: normalize_tags(&entry.tags).ok()?,
+    })
+}
+
+async fn insert_words(
+    conn: &mut SqliteConnection,
+    category_id: i64,
+    entries: Vec<ArchiveWord>,
+) -> Result<WordImportReport, WordListError> {
+    let mut report = WordImportReport::default();
+
+    let mut existing: HashSet<String> =
+        query_scalar!("select word from words where category_id = ?", category_id)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .collect();
+
+    let current_time = OffsetDateTime::now_utc();
+    for entry in entries {
+        let entry = match normalize_archive_word(&entry) {
+            Some(entry) => entry,
+            None => {
+                report.invalid += 1;
+                continue;
+            }
+        };
+        if existing.contains(&entry.word) {
+            report.duplicate += 1;
+        } else {
+            let tags = SqlJson(entry.tags);
+            query!(
+                "insert into words (category_id, word, note, difficulty, tags, created_at, updated_at)
+                values (?, ?, ?, ?, ?, ?, ?)",
+                category_id,
+                entry.word,
+                entry.note,
+                entry.difficulty,
+                tags,
+                current_time,
+                current_time
+            )
+            .execute(&mut *conn)
+            .await?;
+            existing.insert(entry.word);
+            report.added += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Collects all user's categories with their words
+pub async fn export_archive(pool: &SqlitePool, user_id: i64) -> sqlx::Result<WordsArchive> {
+    let categories = query!(
+        r#"select id as "id!", name from categories where user_id = ? order by created_at, id"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut archive_categories = Vec::with_capacity(categories.len());
+    for category in categories {
+        let words = query!(
+            r#"select word, note, difficulty as "difficulty: u8",
+              tags as "tags: SqlJson<Vec<String>>"
+            from words where category_id = ? order by created_at, id"#,
+            category.id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| ArchiveWord {
+            word: row.word,
+            note: row.note,
+            difficulty: row.difficulty,
+            tags: row.tags.0,
+        })
+        .collect();
+        archive_categories.push(ArchiveCategory {
+            name: category.name,
+            words,
+        });
+    }
+
+    Ok(WordsArchive {
+        version: ARCHIVE_VERSION,
+        categories: archive_categories,
+    })
+}
+
+/// Creates categories from archive for user in single transaction
+pub async fn import_archive(
+    pool: &SqlitePool,
+    user_id: i64,
+    archive: WordsArchive,
+) -> Result<ArchiveImportReport, WordListError> {
+    if !(1..=ARCHIVE_VERSION).contains(&archive.version) {
+        return Err(WordListError::UnsupportedArchiveVersion(archive.version));
+    }
+
+    let mut report = ArchiveImportReport::default();
+    let mut transaction = pool.begin().await?;
+    let current_time = OffsetDateTime::now_utc();
+
+    for category in archive.categories {
+        let category_id = query!(
+            "insert into categories (user_id, name, position, created_at, updated_at)
+            values (?, ?, (select coalesce(max(position) + 1, 0) from categories where user_id = ?), ?, ?)",
+            user_id,
+            category.name,
+            user_id,
+            current_time,
+            current_time
+        )
+        .execute(&mut transaction)
+        .await?
+        .last_insert_rowid();
+        let words_report = insert_words(&mut transaction, category_id, category.words).await?;
+
+        report.categories += 1;
+        report.words.added += words_report.added;
+        report.words.duplicate += words_report.duplicate;
+        report.words.invalid += words_report.invalid;
+    }
+
+    transaction.commit().await?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        export_archive, format_word_list, import_archive, import_words, parse_word_list,
+        WordListFormat,
+    };
+    use crate::api_data::{ArchiveWord, WordImportReport};
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_parse_word_list_formats() {
+        assert_eq!(
+            parse_word_list(WordListFormat::Text, "снегирь\nсипуха\n").unwrap(),
+            vec!["снегирь", "сипуха"]
+        );
+        assert_eq!(
+            parse_word_list(
+                WordListFormat::Csv,
+                "word,note\nснегирь,птица\n\"сипуха\"\n"
+            )
+            .unwrap(),
+            vec!["снегирь", "сипуха"]
+        );
+        assert_eq!(
+            parse_word_list(WordListFormat::Json, r#"["снегирь", "сипуха"]"#).unwrap(),
+            vec!["снегирь", "сипуха"]
+        );
+        parse_word_list(WordListFormat::Json, r#"{"word": "снегирь"}"#).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_import_words_counts() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+        let category = add_test_category(&pool, user).await;
+        add_test_named_word(&pool, category, "спазм").await;
+
+        let entries = ["снегирь", " сипуха ", "снегирь", "спазм", "", "   "]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let report = import_words(&pool, category, entries).await.unwrap();
+
+        assert_eq!(
+            report,
+            WordImportReport {
+                added: 2,
+                duplicate: 2,
+                invalid: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_format_word_list_round_trip() {
+        let words = vec![
+            "снегирь".to_owned(),
+            "рыба, \"фиш\"".to_owned(),
+            "сипуха".to_owned(),
+        ];
+        for format in [
+            WordListFormat::Text,
+            WordListFormat::Csv,
+            WordListFormat::Json,
+        ] {
+            let formatted = format_word_list(format, &words);
+            assert_eq!(parse_word_list(format, &formatted).unwrap(), words);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archive_round_trip() {
+        let pool = test_database_pool().await;
+        let user1 = add_test_user(&pool, "user1").await;
+        let user2 = add_test_user(&pool, "user2").await;
+        let category = add_test_category(&pool, user1).await;
+        let word = add_test_named_word(&pool, category, "снегирь").await;
+        add_test_named_word(&pool, category, "сипуха").await;
+        add_test_category(&pool, user1).await;
+        sqlx::query(
+            r#"update words set note = 'птица', difficulty = 2, tags = '["зима"]' where id = ?"#,
+        )
+        .bind(word)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let archive = export_archive(&pool, user1).await.unwrap();
+        assert_eq!(
+            archive.categories[0].words[0],
+            ArchiveWord {
+                word: "снегирь".to_owned(),
+                note: Some("птица".to_owned()),
+                difficulty: Some(2),
+                tags: vec!["зима".to_owned()],
+            }
+        );
+        let serialized = serde_json::to_string(&archive).unwrap();
+        let report = import_archive(&pool, user2, serde_json::from_str(&serialized).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(report.categories, 2);
+        assert_eq!(report.words.added, 2);
+
+        let imported = export_archive(&pool, user2).await.unwrap();
+        assert_eq!(serde_json::to_string(&imported).unwrap(), serialized);
+    }
+
+    #[tokio::test]
+    async fn test_import_archive_version_1() {
+        let pool = test_database_pool().await;
+        let user = add_test_user(&pool, "user").await;
+
+        let archive = r#"{"version": 1, "categories": [{"name": null, "words": ["снегирь"]}]}"#;
+        let report = import_archive(&pool, user, serde_json::from_str(archive).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(report.words.added, 1);
+
+        let imported = export_archive(&pool, user).await.unwrap();
+        assert_eq!(
+            imported.categories[0].words,
+            vec![ArchiveWord::from("снегирь".to_owned())]
+        );
+    }
+}
@@ -3,6 +3,10 @@ import axios from 'axios';
 export let client = axios.create({
     baseURL: '/api/v1',
     timeout: 30000,
+    // Double-submit CSRF protection: server sets this cookie and expects
+    // its value back in header on every non-GET request
+    xsrfCookieName: 'pss_csrf',
+    xsrfHeaderName: 'X-CSRF-Token',
 });
 
 client.interceptors.response.use(
@@ -25,14 +29,29 @@ type CategoryList = { categories: Category[] };
 type Word = {
     id: number,
     word: string,
+    note: string | null,
+    difficulty: number | null,
+    tags: string[],
+    created_at: string,
 };
 
-type WordList = { words: Word[] };
+type WordList = {
+    words: Word[],
+    next_cursor: string | null,
+    total: number,
+};
+
+type WordListParams = {
+    sort?: 'alphabetical' | 'newest' | 'oldest',
+    q?: string,
+    cursor?: string,
+    limit?: number,
+};
 
 export async function listCategories(): Promise<CategoryList> {
     return client.get('words').then((r) => r.data);
 }
 
-export async function listWords(category_id: number): Promise<WordList> {
-    return client.get(`words/${category_id}`).then((r) => r.data);
+export async function listWords(category_id: number, params: WordListParams = {}): Promise<WordList> {
+    return client.get(`words/${category_id}`, { params }).then((r) => r.data);
 }